@@ -0,0 +1,253 @@
+//! Due dates and recurrence, and parsing dates written like `tomorrow 9am`
+//! or `next friday`, as used by quick add and `--parse-date`.
+
+use chrono::{
+    Datelike, Days, Local, Month, Months, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Timelike,
+    Weekday,
+};
+
+pub fn now() -> NaiveDateTime {
+    Local::now().naive_local()
+}
+
+/// A due date, with the time of day if one was given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Due {
+    pub date: NaiveDate,
+    pub time: Option<NaiveTime>,
+}
+
+impl Due {
+    /// Whether it has passed. Dates without a time last the whole day.
+    pub fn is_past(self, now: NaiveDateTime) -> bool {
+        match self.time {
+            Some(time) => self.date.and_time(time) < now,
+            None => self.date < now.date(),
+        }
+    }
+
+    /// As understood by `parse_date`, e.g. `2024-06-30` or `2024-06-30T09:00`.
+    pub fn iso(self) -> String {
+        match self.time {
+            Some(time) => format!("{}T{}", self.date.format("%Y-%m-%d"), time.format("%H:%M")),
+            None => self.date.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// Relative to today where that reads better, e.g. `tomorrow 09:00`.
+    pub fn fmt(self, today: NaiveDate) -> String {
+        let date = match (self.date - today).num_days() {
+            0 => "today".to_string(),
+            1 => "tomorrow".to_string(),
+            -1 => "yesterday".to_string(),
+            _ if self.date.year() == today.year() => self.date.format("%a %-d %b").to_string(),
+            _ => self.date.format("%a %-d %b %Y").to_string(),
+        };
+        match self.time {
+            Some(time) => format!("{date} {}", time.format("%H:%M")),
+            None => date,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+/// Repeat every `every` units, counted from the due date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recurrence {
+    pub every: u32,
+    pub unit: Unit,
+}
+
+impl Recurrence {
+    pub fn next(self, date: NaiveDate) -> Option<NaiveDate> {
+        match self.unit {
+            Unit::Day   => date.checked_add_days(Days::new(self.every.into())),
+            Unit::Week  => date.checked_add_days(Days::new(u64::from(self.every) * 7)),
+            Unit::Month => date.checked_add_months(Months::new(self.every)),
+            Unit::Year  => date.checked_add_months(Months::new(self.every.checked_mul(12)?)),
+        }
+    }
+
+    /// As written after `every:`, e.g. `week` or `2weeks`.
+    pub fn fmt(self) -> String {
+        let unit = match self.unit {
+            Unit::Day   => "day",
+            Unit::Week  => "week",
+            Unit::Month => "month",
+            Unit::Year  => "year",
+        };
+        match self.every {
+            1 => unit.to_string(),
+            n => format!("{n}{unit}s"),
+        }
+    }
+}
+
+/// A number made of digits only.
+fn number(word: &str) -> Option<u32> {
+    word.chars().all(|c| c.is_ascii_digit()).then(|| word.parse().ok()).flatten()
+}
+
+/// Time of day such as `9am`, `9:30pm`, `21:00`, `noon` or `midnight`.
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    match word {
+        "noon" | "midday" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+    let (clock, half) = match (word.strip_suffix("am"), word.strip_suffix("pm")) {
+        (Some(clock), _) => (clock, Some(0)),
+        (_, Some(clock)) => (clock, Some(12)),
+        _ => (word, None),
+    };
+    let (hour, minute) = match clock.split_once(':') {
+        Some((hour, minute)) if minute.len() == 2 => (number(hour)?, number(minute)?),
+        None if half.is_some() => (number(clock)?, 0),
+        _ => return None,
+    };
+    let hour = match half {
+        Some(half) if (1..=12).contains(&hour) => hour % 12 + half,
+        Some(_) => return None,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Day of the month such as `30`, `1st` or `22nd`.
+fn parse_day(word: &str) -> Option<u32> {
+    let digits = ["st", "nd", "rd", "th"].iter().find_map(|s| word.strip_suffix(s)).unwrap_or(word);
+    number(digits).filter(|d| (1..=31).contains(d))
+}
+
+/// Count in phrases like `in 3 days` or `in a week`.
+fn parse_amount(word: &str) -> Option<u32> {
+    match word {
+        "a" | "an" | "one" => Some(1),
+        _ => number(word),
+    }
+}
+
+/// The coming weekday, today included unless `skip_today`.
+fn upcoming(today: NaiveDate, weekday: Weekday, skip_today: bool) -> Option<NaiveDate> {
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 && skip_today { 7 } else { ahead };
+    today.checked_add_days(Days::new(ahead.into()))
+}
+
+/// `jun 30` or `30 jun` in the given year, or else the next one to come.
+fn month_day(today: NaiveDate, a: &str, b: &str, year: Option<&str>) -> Option<NaiveDate> {
+    let (month, day) = match (a.parse::<Month>(), b.parse::<Month>()) {
+        (Ok(month), Err(_)) => (month, b),
+        (Err(_), Ok(month)) => (month, a),
+        _ => return None,
+    };
+    let (month, day) = (month.number_from_month(), parse_day(day)?);
+    match year {
+        Some(year) if year.len() == 4 => {
+            NaiveDate::from_ymd_opt(i32::try_from(number(year)?).ok()?, month, day)
+        }
+        Some(_) => None,
+        None => match NaiveDate::from_ymd_opt(today.year(), month, day)? {
+            date if date < today => NaiveDate::from_ymd_opt(today.year() + 1, month, day),
+            date => Some(date),
+        },
+    }
+}
+
+/// Resolve a due date such as `tomorrow 9am`, `next monday`, `in 3 days`,
+/// `end of month`, `jun 30` or `2024-06-30` in local time relative to
+/// `now`. Weekdays mean the coming one, today included; `next` skips today.
+pub fn parse_date(text: &str, now: NaiveDateTime) -> Option<Due> {
+    let today = now.date();
+    let text = text.to_lowercase().replace(',', " ");
+
+    // Words, with `9 am` joined and `2024-06-30t09:00` split.
+    let mut words: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        if let (Some(last), "am" | "pm") = (words.last_mut(), word) {
+            if parse_time(&format!("{last}{word}")).is_some() {
+                last.push_str(word);
+                continue;
+            }
+        }
+        match word.split_once('t') {
+            Some((date, time)) if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok() => {
+                words.push(date.to_string());
+                words.push(time.to_string());
+            }
+            _ => words.push(word.to_string()),
+        }
+    }
+
+    // The time of day may come anywhere, optionally after `at`.
+    let mut time = None;
+    let mut rest: Vec<&str> = Vec::new();
+    for word in &words {
+        match parse_time(word) {
+            Some(t) if time.is_none() => {
+                if rest.last() == Some(&"at") {
+                    rest.pop();
+                }
+                time = Some(t);
+            }
+            _ => rest.push(word),
+        }
+    }
+
+    let weekday = |word: &str| word.parse::<Weekday>().ok();
+    let date = match rest.as_slice() {
+        [] => {
+            // A time alone is the next one to come.
+            let time = time?;
+            let date = if today.and_time(time) < now { today.succ_opt()? } else { today };
+            return Some(Due { date, time: Some(time) });
+        }
+        ["now"] if time.is_none() => {
+            let time = NaiveTime::from_hms_opt(now.hour(), now.minute(), 0);
+            return Some(Due { date: today, time });
+        }
+        ["in", amount, unit] if time.is_none() && matches!(*unit, "hour" | "hours" | "minute" | "minutes") => {
+            let amount = i64::from(parse_amount(amount)?);
+            let delta = if unit.starts_with('h') { TimeDelta::try_hours(amount) } else { TimeDelta::try_minutes(amount) };
+            let at = now.checked_add_signed(delta?)?;
+            let time = NaiveTime::from_hms_opt(at.hour(), at.minute(), 0);
+            return Some(Due { date: at.date(), time });
+        }
+        ["today" | "tod"] => today,
+        ["tomorrow" | "tom" | "tmr"] => today.succ_opt()?,
+        ["yesterday"] => today.pred_opt()?,
+        [day] | ["this", day] if weekday(day).is_some() => upcoming(today, weekday(day)?, false)?,
+        ["next", day] if weekday(day).is_some() => upcoming(today, weekday(day)?, true)?,
+        ["next", "week"] => today.checked_add_days(Days::new(7))?,
+        ["next", "month"] => today.checked_add_months(Months::new(1))?,
+        ["next", "year"] => today.checked_add_months(Months::new(12))?,
+        ["in", amount, unit] => {
+            let unit = match *unit {
+                "day" | "days" => Unit::Day,
+                "week" | "weeks" => Unit::Week,
+                "month" | "months" => Unit::Month,
+                "year" | "years" => Unit::Year,
+                _ => return None,
+            };
+            Recurrence { every: parse_amount(amount)?, unit }.next(today)?
+        }
+        ["end", "of", "week"] | ["eow"] => upcoming(today, Weekday::Sun, false)?,
+        ["end", "of", "month"] | ["eom"] => {
+            let first = NaiveDate::from_ymd_opt(today.year(), today.month(), 1)?;
+            first.checked_add_months(Months::new(1))?.pred_opt()?
+        }
+        ["end", "of", "year"] | ["eoy"] => NaiveDate::from_ymd_opt(today.year(), 12, 31)?,
+        [a, b] => month_day(today, a, b, None)?,
+        [a, b, year] => month_day(today, a, b, Some(year))?,
+        [iso] => NaiveDate::parse_from_str(iso, "%Y-%m-%d").ok()?,
+        _ => return None,
+    };
+    Some(Due { date, time })
+}
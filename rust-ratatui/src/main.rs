@@ -1,22 +1,74 @@
+use chrono::NaiveDateTime;
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+        MouseEventKind,
+    },
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     ExecutableCommand,
 };
+use icu_collator::{Collator, CollatorOptions, Strength};
+use icu_locid::Locale;
 use ratatui::{
-    prelude::{Alignment, Color, CrosstermBackend, Line, Rect, Style, Stylize, Terminal, Frame},
-    widgets::{Block, BorderType, List, ListState, Padding, Paragraph},
+    prelude::{
+        Alignment, Color, CrosstermBackend, Line, Margin, Rect, Span, Style, Stylize, Terminal, Text,
+        Frame,
+    },
+    widgets::{
+        Block, Clear, List, ListState, Padding, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState,
+    },
+    symbols::{border, scrollbar},
 };
 use std::{
-    format,
-    io::{stdout, Result, Stdout},
+    cmp::Reverse,
+    env, format, fs,
+    io::{stdout, ErrorKind, Read, Result, Stdout, Write},
+    ops::Range,
+    path::PathBuf,
+    time::{Duration, Instant},
 };
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+mod date;
+use date::{now, parse_date, Due, Recurrence, Unit};
+
+/// Maximum delay between two clicks for them to count as a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
 
 // todos //////////////////////////////////////////////////////////////
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Priority {
+    Low,
+    Medium,
+    High,
+}
+
+impl Priority {
+    fn name(self) -> &'static str {
+        match self {
+            Priority::Low    => "low",
+            Priority::Medium => "medium",
+            Priority::High   => "high",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
 struct Todo {
     name: String,
     complete: bool,
+    tags: Vec<String>,
+    priority: Option<Priority>,
+    due: Option<Due>,
+    recurrence: Option<Recurrence>,
+    completed_at: Option<NaiveDateTime>,
+    /// Picked for a bulk operation.
+    marked: bool,
+    /// Being worked on, in the board's "In progress" column.
+    started: bool,
 }
 
 impl Todo {
@@ -24,16 +76,124 @@ impl Todo {
         Self {
             name,
             complete: false,
+            tags: Vec::new(),
+            priority: None,
+            due: None,
+            recurrence: None,
+            completed_at: None,
+            marked: false,
+            started: false,
+        }
+    }
+
+    /// Todo with metadata from quick-add syntax, see `parse_quick_add`.
+    fn from_quick_add(input: &str, now: NaiveDateTime) -> Self {
+        let add = parse_quick_add(input, now);
+        Self {
+            tags: add.tags,
+            priority: add.priority,
+            due: add.due,
+            recurrence: add.recurrence,
+            ..Self::new(add.name)
         }
     }
 
     fn toggle(&mut self) {
-        self.complete = !self.complete;
+        if self.complete {
+            self.complete = false;
+            self.completed_at = None;
+        } else {
+            self.complete(now());
+        }
+    }
+
+    /// Mark as complete. Recurring todos move on to their next due date
+    /// instead.
+    fn complete(&mut self, now: NaiveDateTime) {
+        self.started = false;
+        match self.recurrence {
+            Some(r) => {
+                let due = self.due.unwrap_or(Due { date: now.date(), time: None });
+                self.due = r.next(due.date).map(|date| Due { date, ..due });
+            }
+            None => {
+                self.complete = true;
+                self.completed_at = Some(now);
+            }
+        }
+    }
+
+    /// Quick-add syntax that gives back this todo when parsed on `now`, for
+    /// editing.
+    fn quick_add(&self, now: NaiveDateTime) -> String {
+        let mut words = vec![escape_quick_add(&self.name, now)];
+        words.extend(self.tags.iter().map(|t| format!("#{t}")));
+        if let Some(p) = self.priority {
+            words.push(format!("!{}", p.name()));
+        }
+        if let Some(due) = self.due {
+            words.push(format!("due:{}", due.iso()));
+        }
+        if let Some(r) = self.recurrence {
+            words.push(format!("every:{}", r.fmt()));
+        }
+        words.join(" ")
+    }
+
+    /// Name and tags as shown in the list, for searching.
+    fn search_text(&self) -> String {
+        self.tags.iter().fold(self.name.clone(), |text, tag| format!("{text} #{tag}"))
+    }
+
+    /// Row in the list, with the chars of `search_text` at `matched` indices
+    /// highlighted.
+    fn fmt_item(&self, now: NaiveDateTime, matched: &[usize], theme: &Theme) -> Text<'_> {
+        let marker = if self.marked { theme.marker.clone() } else { " ".repeat(theme.marker.width()) };
+        let checkbox = if self.complete { &theme.checked } else { &theme.unchecked };
+        let mut spans = vec![Span::styled(format!(" {marker} "), theme.marker_style), format!("{checkbox} ").into()];
+        spans.extend(highlight(&self.name, 0, matched, Style::default(), theme.matched));
+        let mut offset = self.name.chars().count();
+        for tag in &self.tags {
+            let text = format!(" #{tag}");
+            spans.extend(highlight(&text, offset, matched, theme.tag, theme.matched));
+            offset += text.chars().count();
+        }
+        if let Some(p) = self.priority {
+            spans.push(Span::styled(format!(" !{}", p.name()), theme.priority_style(p)));
+        }
+        if let Some(due) = self.due {
+            let style = if self.complete {
+                theme.dim
+            } else if due.is_past(now) {
+                theme.overdue
+            } else if due.date == now.date() {
+                theme.due_today
+            } else {
+                theme.due
+            };
+            spans.push(Span::styled(format!(" due {}", due.fmt(now.date())), style));
+        }
+        if let Some(r) = self.recurrence {
+            spans.push(Span::styled(format!(" {} {}", theme.repeat, r.fmt()), theme.dim));
+        }
+        Text::from(vec![Line::default(), Line::from(spans), Line::default()])
     }
 
-    fn fmt_item(&self) -> String {
-        let middle = (if self.complete { "(X) " } else { "( ) " }).to_string() + &self.name;
-        format!("\n   {middle}\n\n")
+    /// Card on the board, kept to one line.
+    fn fmt_card(&self, now: NaiveDateTime, theme: &Theme) -> Line<'_> {
+        let mut spans = Vec::new();
+        if self.marked {
+            spans.push(Span::styled(format!("{} ", theme.marker), theme.marker_style));
+        }
+        spans.push(Span::styled(self.name.as_str(), if self.complete { theme.dim } else { Style::default() }));
+        if let Some(p) = self.priority {
+            spans.push(Span::styled(format!(" !{}", p.name()), theme.priority_style(p)));
+        }
+        if let Some(due) = self.due.filter(|_| !self.complete) {
+            let style = if due.is_past(now) { theme.overdue } else { theme.dim };
+            spans.push(Span::styled(format!(" {}", due.fmt(now.date())), style));
+        }
+        Line::from(spans)
     }
 }
 
@@ -48,7 +208,558 @@ fn fmt_itemsleft(ts: &Todos) -> String {
     }
 }
 
+/// Todos from pasted text, one per non-empty line, with list bullets and
+/// markdown checkboxes stripped. Checked boxes give completed todos, and
+/// the rest of each line is read as quick-add input, so exports paste back.
+fn parse_pasted(text: &str, now: NaiveDateTime) -> Todos {
+    let mut todos = Vec::new();
+    for line in text.lines() {
+        let mut line = line.trim();
+        // Bullets: "-", "*", "+", "•", or numbered like "1." and "2)".
+        if let Some(rest) = line.strip_prefix(['-', '*', '+', '•']) {
+            line = rest.trim_start();
+        } else if let Some(digits) = line.find(|c: char| !c.is_ascii_digit()).filter(|&i| i > 0) {
+            if let Some(rest) = line[digits..].strip_prefix(['.', ')']) {
+                line = rest.trim_start();
+            }
+        }
+        let mut complete = false;
+        if let Some(rest) = line.strip_prefix("[ ]") {
+            line = rest.trim_start();
+        } else if let Some(rest) = line.strip_prefix("[x]").or_else(|| line.strip_prefix("[X]")) {
+            line = rest.trim_start();
+            complete = true;
+        }
+        if !line.is_empty() {
+            todos.push(Todo { complete, ..Todo::from_quick_add(line, now) });
+        }
+    }
+    todos
+}
+
+// Quick add //////////////////////////////////////////////////////////
+/// Kinds of words recognised by `parse_quick_add`, for highlighting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token {
+    Tag,
+    Priority,
+    Due,
+    Recurrence,
+    /// The backslash in front of a literal word.
+    Escape,
+    /// Looks like metadata but the value is not understood.
+    Invalid,
+}
+
+impl Token {
+    fn style(self, theme: &Theme) -> Style {
+        match self {
+            Token::Tag        => theme.tag,
+            Token::Priority   => theme.priority,
+            Token::Due        => theme.due,
+            Token::Recurrence => theme.recurrence,
+            Token::Escape     => theme.dim,
+            Token::Invalid    => theme.invalid,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct QuickAdd {
+    name: String,
+    tags: Vec<String>,
+    priority: Option<Priority>,
+    due: Option<Due>,
+    recurrence: Option<Recurrence>,
+    /// Byte ranges of recognised words.
+    tokens: Vec<(Range<usize>, Token)>,
+}
+
+/// Byte ranges of whitespace-separated words. A quote right after a colon,
+/// as in `due:"next friday"`, keeps spaces up to the closing quote.
+fn quick_add_words(input: &str) -> Vec<Range<usize>> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut quoted = false;
+    let mut prev = ' ';
+    for (i, c) in input.char_indices() {
+        if quoted {
+            quoted = c != '"';
+        } else if c.is_whitespace() {
+            if let Some(s) = start.take() {
+                words.push(s..i);
+            }
+        } else {
+            start.get_or_insert(i);
+            quoted = c == '"' && prev == ':';
+        }
+        prev = c;
+    }
+    if let Some(s) = start {
+        words.push(s..input.len());
+    }
+    words
+}
+
+fn unquote(value: &str) -> &str {
+    match value.strip_prefix('"') {
+        Some(v) => v.strip_suffix('"').unwrap_or(v),
+        None => value,
+    }
+}
+
+fn parse_priority(text: &str) -> Option<Priority> {
+    match text.to_lowercase().as_str() {
+        "high" | "h" | "1" => Some(Priority::High),
+        "medium" | "med" | "m" | "2" => Some(Priority::Medium),
+        "low" | "l" | "3" => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+/// Recurrence such as `day`, `weekly` or `2weeks`.
+fn parse_recurrence(text: &str) -> Option<Recurrence> {
+    let text = text.to_lowercase();
+    let digits = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let every = match digits {
+        0 => 1,
+        _ => text[..digits].parse().ok().filter(|&n| n > 0)?,
+    };
+    let unit = match (digits, &text[digits..]) {
+        (_, "day" | "days") | (0, "daily") => Unit::Day,
+        (_, "week" | "weeks") | (0, "weekly") => Unit::Week,
+        (_, "month" | "months") | (0, "monthly") => Unit::Month,
+        (_, "year" | "years") | (0, "yearly") => Unit::Year,
+        _ => return None,
+    };
+    Some(Recurrence { every, unit })
+}
+
+/// The due date starting in `words[i]` after `due:`, read on into as many
+/// of the next few words as still make a date. Gives the date and the end
+/// of the words used.
+fn quick_add_due(input: &str, words: &[Range<usize>], i: usize, now: NaiveDateTime) -> Option<(Due, usize)> {
+    let start = words[i].start + "due:".len();
+    if input[start..].starts_with('"') {
+        return parse_date(unquote(&input[start..words[i].end]), now).map(|due| (due, i + 1));
+    }
+    (i..words.len().min(i + 4)).rev().find_map(|j| {
+        parse_date(&input[start..words[j].end], now).map(|due| (due, j + 1))
+    })
+}
+
+/// Split new-todo input like `Ship release #work !high due:fri every:week`
+/// into the name and metadata. A leading backslash keeps a word literal.
+fn parse_quick_add(input: &str, now: NaiveDateTime) -> QuickAdd {
+    let mut add = QuickAdd::default();
+    let mut name = Vec::new();
+    let words = quick_add_words(input);
+    let mut i = 0;
+    while i < words.len() {
+        let mut range = words[i].clone();
+        let word = &input[range.clone()];
+        i += 1;
+        let token = if let Some(literal) = word.strip_prefix('\\') {
+            name.push(literal);
+            add.tokens.push((range.start..range.start + 1, Token::Escape));
+            continue;
+        } else if let Some(tag) = word.strip_prefix('#').filter(|t| !t.is_empty() && !t.chars().all(|c| c.is_ascii_digit())) {
+            if !add.tags.iter().any(|t| t == tag) {
+                add.tags.push(tag.to_string());
+            }
+            Token::Tag
+        } else if let Some(priority) = word.strip_prefix('!').and_then(parse_priority) {
+            add.priority = Some(priority);
+            Token::Priority
+        } else if word.starts_with("due:") {
+            match quick_add_due(input, &words, i - 1, now) {
+                Some((due, end)) => {
+                    add.due = Some(due);
+                    range.end = words[end - 1].end;
+                    i = end;
+                    Token::Due
+                }
+                None => {
+                    name.push(word);
+                    Token::Invalid
+                }
+            }
+        } else if let Some(value) = word.strip_prefix("every:") {
+            match parse_recurrence(unquote(value)) {
+                Some(recurrence) => {
+                    add.recurrence = Some(recurrence);
+                    Token::Recurrence
+                }
+                None => {
+                    name.push(word);
+                    Token::Invalid
+                }
+            }
+        } else {
+            name.push(word);
+            continue;
+        };
+        add.tokens.push((range, token));
+    }
+    add.name = name.join(" ");
+    add
+}
+
+/// Escape words of a name that would otherwise be parsed as metadata on
+/// `now`, including `due:` words that only make a date with the words
+/// after them.
+fn escape_quick_add(name: &str, now: NaiveDateTime) -> String {
+    let mut escaped = name.to_string();
+    let words = quick_add_words(name);
+    for (i, range) in words.iter().enumerate().rev() {
+        let word = &name[range.clone()];
+        let is_metadata = if word.starts_with("due:") {
+            quick_add_due(name, &words, i, now).is_some()
+        } else {
+            parse_quick_add(word, now).tokens.iter().any(|(_, token)| *token != Token::Invalid)
+        };
+        if is_metadata || word.starts_with('\\') {
+            escaped.insert(range.start, '\\');
+        }
+    }
+    escaped
+}
+
+// Completion /////////////////////////////////////////////////////////
+/// Offered after `due:`.
+const DATE_KEYWORDS: [&str; 15] = [
+    "today", "tomorrow", "monday", "tuesday", "wednesday", "thursday", "friday", "saturday",
+    "sunday", "next week", "next month", "next year", "end of week", "end of month", "end of year",
+];
+
+/// Completions for the `#tag` or `due:` word before the cursor, or for the
+/// list name asked for by a prompt.
+#[derive(Debug, Default)]
+struct Completion {
+    /// Byte index where the completed text starts, after `#` or `due:`.
+    start: usize,
+    /// The text being completed, up to the cursor.
+    prefix: String,
+    items: Vec<String>,
+    selected: Option<usize>,
+}
+
+impl Completion {
+    /// Completions for the word ending at byte `end` of the input, with tags
+    /// taken from the todos, and list names that could be tags.
+    fn new(input: &str, end: usize, todos: &Todos, lists: &[&str]) -> Self {
+        let before = &input[..end];
+        let word = before.rsplit(char::is_whitespace).next().unwrap_or_default();
+        let (prefix, candidates) = if let Some(prefix) = word.strip_prefix('#') {
+            let mut tags: Vec<String> = todos.iter().flat_map(|t| t.tags.iter().cloned()).collect();
+            tags.sort_unstable();
+            tags.dedup();
+            for name in lists.iter().filter(|n| !n.is_empty() && !n.contains(char::is_whitespace)) {
+                if !tags.iter().any(|t| t == name) {
+                    tags.push(name.to_string());
+                }
+            }
+            (prefix, tags)
+        } else if let Some(prefix) = word.strip_prefix("due:") {
+            (prefix, DATE_KEYWORDS.map(String::from).to_vec())
+        } else {
+            return Self::default();
+        };
+        Self::matching(prefix, end - prefix.len(), candidates)
+    }
+
+    /// Completions of the whole input up to byte `end` from list names, for
+    /// the prompts that ask for a list.
+    fn lists(input: &str, end: usize, names: &[&str]) -> Self {
+        Self::matching(&input[..end], 0, names.iter().map(|n| n.to_string()).collect())
+    }
+
+    /// Candidates that start with the prefix, ignoring case, other than the
+    /// prefix itself.
+    fn matching(prefix: &str, start: usize, candidates: Vec<String>) -> Self {
+        let lower = prefix.to_lowercase();
+        let items = candidates.into_iter()
+            .filter(|c| c != prefix && c.to_lowercase().starts_with(&lower))
+            .collect();
+        Self { start, prefix: prefix.to_string(), items, selected: None }
+    }
+
+    /// Select the next or previous item, wrapping around.
+    fn cycle(&mut self, forward: bool) {
+        let n = self.items.len();
+        if n > 0 {
+            self.selected = Some(match (self.selected, forward) {
+                (None, true) => 0,
+                (None, false) => n - 1,
+                (Some(i), true) => (i + 1) % n,
+                (Some(i), false) => (i + n - 1) % n,
+            });
+        }
+    }
+}
+
+// Search /////////////////////////////////////////////////////////////
+/// Score of `query` as a subsequence of `text`, with the char indices that
+/// matched. Case-insensitive unless the query has capitals, preferring
+/// consecutive matches and matches at the start of words.
+fn fuzzy_match(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let smart = query.chars().any(char::is_uppercase);
+    let fold = |c: char| if smart { c } else { c.to_lowercase().next().unwrap_or(c) };
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let text: Vec<char> = text.chars().collect();
+    let Some((&first, rest)) = query.split_first() else {
+        return Some((0, Vec::new()));
+    };
+    let int = |n: usize| i64::try_from(n).unwrap_or(i64::MAX);
+
+    // Match the rest greedily from each place the first character matches.
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in (0..text.len()).filter(|&i| fold(text[i]) == first) {
+        let mut indices = vec![start];
+        for &q in rest {
+            let from = indices[indices.len() - 1] + 1;
+            match (from..text.len()).find(|&i| fold(text[i]) == q) {
+                Some(i) => indices.push(i),
+                None => return best,
+            }
+        }
+        let score = indices.iter().enumerate().map(|(n, &i)| {
+            let boundary = i == 0 || !text[i - 1].is_alphanumeric();
+            let gap = if n == 0 { start } else { i - indices[n - 1] - 1 };
+            16 + 8 * i64::from(boundary) + 8 * i64::from(n > 0 && gap == 0) - int(gap).min(8)
+        }).sum();
+        if best.as_ref().is_none_or(|(b, _)| *b < score) {
+            best = Some((score, indices));
+        }
+    }
+    best
+}
+
+/// Spans of `text` in a style, with the chars at `matched` indices, counted
+/// from `offset`, highlighted in the `hit` style.
+fn highlight(text: &str, offset: usize, matched: &[usize], style: Style, hit: Style) -> Vec<Span<'static>> {
+    let mut runs: Vec<(String, bool)> = Vec::new();
+    for (i, c) in text.chars().enumerate() {
+        let hit = matched.contains(&(offset + i));
+        match runs.last_mut() {
+            Some((run, h)) if *h == hit => run.push(c),
+            _ => runs.push((c.to_string(), hit)),
+        }
+    }
+    runs.into_iter()
+        .map(|(run, is_hit)| Span::styled(run, if is_hit { style.patch(hit) } else { style }))
+        .collect()
+}
+
+/// `/` search over todo names and tags.
+#[derive(Debug, Default)]
+struct Search {
+    query: String,
+    /// Whether the query is being typed, with the list filtered to matches.
+    typing: bool,
+    /// Selection and scroll offset from before the search, for escape.
+    previous: (Option<usize>, usize),
+}
+
+// Sorting ////////////////////////////////////////////////////////////
+/// Name of the list shown at startup.
+const DEFAULT_LIST: &str = "Todos";
+
+/// Order the list is shown in. The todos themselves keep insertion order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum Sort {
+    #[default]
+    Insertion,
+    Alphabetical,
+    ActiveFirst,
+    RecentlyCompleted,
+    DueDate,
+    Priority,
+}
+
+const SORTS: [Sort; 6] = [
+    Sort::Insertion,
+    Sort::Alphabetical,
+    Sort::ActiveFirst,
+    Sort::RecentlyCompleted,
+    Sort::DueDate,
+    Sort::Priority,
+];
+
+/// Collator for sorting names by the rules of a POSIX locale like
+/// `sv_SE.UTF-8`, or the root locale's if it isn't known. Case and accents
+/// are ignored, so "école" sorts with "ecole" rather than after "zoo",
+/// unless the locale makes them letters of their own, as Swedish does "å".
+fn collator(locale: &str) -> Collator {
+    let tag = locale.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+    let locale = tag.parse::<Locale>().unwrap_or_default();
+    let mut options = CollatorOptions::new();
+    options.strength = Some(Strength::Primary);
+    Collator::try_new(&locale.into(), options)
+        .or_else(|_| Collator::try_new(&Default::default(), options))
+        .expect("collation data for the root locale is built in")
+}
+
+/// The locale names are sorted in, from the environment.
+fn collation_locale() -> String {
+    ["LC_ALL", "LC_COLLATE", "LANG"].iter()
+        .find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))
+        .unwrap_or_default()
+}
+
+impl Sort {
+    fn name(self) -> &'static str {
+        match self {
+            Sort::Insertion         => "Insertion order",
+            Sort::Alphabetical      => "Alphabetical",
+            Sort::ActiveFirst       => "Active first",
+            Sort::RecentlyCompleted => "Recently completed",
+            Sort::DueDate           => "Due date",
+            Sort::Priority          => "Priority",
+        }
+    }
+
+    /// Name in the persisted file.
+    fn key(self) -> &'static str {
+        match self {
+            Sort::Insertion         => "insertion",
+            Sort::Alphabetical      => "alphabetical",
+            Sort::ActiveFirst       => "active-first",
+            Sort::RecentlyCompleted => "recently-completed",
+            Sort::DueDate           => "due-date",
+            Sort::Priority          => "priority",
+        }
+    }
+
+    /// Indices of todos in the order they are shown. Sorting is stable, so
+    /// ties keep insertion order.
+    fn order(self, todos: &Todos, collator: &Collator) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..todos.len()).collect();
+        match self {
+            Sort::Insertion => {}
+            Sort::Alphabetical => order.sort_by(|&a, &b| collator.compare(&todos[a].name, &todos[b].name)),
+            Sort::ActiveFirst => order.sort_by_key(|&i| todos[i].complete),
+            // Most recent first, then the active ones.
+            Sort::RecentlyCompleted => order.sort_by_key(|&i| Reverse(todos[i].completed_at)),
+            Sort::DueDate => order.sort_by_key(|&i| (todos[i].due.is_none(), todos[i].due)),
+            Sort::Priority => order.sort_by_key(|&i| Reverse(todos[i].priority)),
+        }
+        order
+    }
+}
+
+/// Sort mode chosen for each list, persisted as `list<TAB>mode` lines.
+#[derive(Debug, Default)]
+struct SortModes {
+    modes: Vec<(String, Sort)>,
+    path: Option<PathBuf>,
+}
+
+impl SortModes {
+    /// Load sort modes from a file, if it exists, skipping unknown lines.
+    fn load(path: Option<PathBuf>) -> Self {
+        let mut modes = Vec::new();
+        if let Some(content) = path.as_ref().and_then(|p| fs::read_to_string(p).ok()) {
+            for (list, key) in content.lines().filter_map(|l| l.split_once('\t')) {
+                if let Some(sort) = SORTS.into_iter().find(|s| s.key() == key) {
+                    modes.push((list.to_string(), sort));
+                }
+            }
+        }
+        Self { modes, path }
+    }
+
+    fn get(&self, list: &str) -> Sort {
+        self.modes.iter().find(|(l, _)| l == list).map_or(Sort::default(), |(_, sort)| *sort)
+    }
+
+    /// Set the mode for a list and persist it. Failing to write is ignored.
+    fn set(&mut self, list: &str, sort: Sort) {
+        self.modes.retain(|(l, _)| l != list);
+        self.modes.push((list.to_string(), sort));
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            let lines: String = self.modes.iter().map(|(l, s)| format!("{l}\t{}\n", s.key())).collect();
+            let _ = fs::write(path, lines);
+        }
+    }
+}
+
+// History ////////////////////////////////////////////////////////////
+/// Maximum number of undo steps kept.
+const HISTORY_LIMIT: usize = 200;
+
+/// Todos and list selection that can be returned to with undo and redo.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    todolist: Todos,
+    selected: Option<usize>,
+    list: String,
+    lists: Vec<(String, Todos)>,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    /// Record the state before a mutation, discarding anything to redo.
+    fn push(&mut self, snapshot: Snapshot) {
+        self.undo.push(snapshot);
+        self.redo.clear();
+        if self.undo.len() > HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    /// Step back, given the current state to be redone later.
+    fn undo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let prev = self.undo.pop()?;
+        self.redo.push(current);
+        Some(prev)
+    }
+
+    /// Step forward, given the current state to be undone later.
+    fn redo(&mut self, current: Snapshot) -> Option<Snapshot> {
+        let next = self.redo.pop()?;
+        self.undo.push(current);
+        Some(next)
+    }
+}
+
 // Input //////////////////////////////////////////////////////////////
+/// Maximum number of killed texts kept for yanking.
+const KILL_RING_LIMIT: usize = 16;
+
+/// What the last input command did with the kill ring, so that
+/// consecutive kills join into one entry and yank-pop can replace a yank.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum KillState {
+    #[default]
+    None,
+    Killed,
+    /// Yanked the kill ring entry at `index`, inserted from `start` up to
+    /// the cursor.
+    Yanked { start: usize, index: usize },
+}
+
+/// Whether a grapheme is part of a word, for word motions and selection.
+fn is_word(g: &str) -> bool {
+    g.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+}
+
+/// Whether a grapheme is part of a whitespace-delimited word, for ctrl-w.
+fn is_unix_word(g: &str) -> bool {
+    !g.starts_with(char::is_whitespace)
+}
+
+/// Text input where the cursor and selection are indices of extended
+/// grapheme clusters, so a character with combining marks, or an emoji
+/// sequence, is moved over and deleted as a whole.
 #[derive(Debug)]
 struct Inputter {
     input: String,
@@ -56,6 +767,16 @@ struct Inputter {
     saved_input: String,
     saved_cursor: usize,
     render_placeholder: bool,
+    /// Other end of the selection, which spans from here to the cursor.
+    anchor: Option<usize>,
+    /// Display column of the input shown first, when it is too long to fit.
+    scroll: usize,
+    /// Killed text, most recent last.
+    kill_ring: Vec<String>,
+    /// What the current command did with the kill ring.
+    kill_state: KillState,
+    /// What the previous command did with the kill ring.
+    last_kill_state: KillState,
 }
 
 impl Inputter {
@@ -66,15 +787,33 @@ impl Inputter {
             saved_input: String::new(),
             saved_cursor: 0,
             render_placeholder: true,
+            anchor: None,
+            scroll: 0,
+            kill_ring: Vec::new(),
+            kill_state: KillState::None,
+            last_kill_state: KillState::None,
         }
     }
 
     fn cursor_to_start(&mut self) {
         self.cursor = 0;
+        self.anchor = None;
     }
 
     fn cursor_to_end(&mut self) {
-        self.cursor = self.input.chars().count();
+        self.cursor = self.len();
+        self.anchor = None;
+    }
+
+    /// Number of graphemes in the input.
+    fn len(&self) -> usize {
+        self.input.graphemes(true).count()
+    }
+
+    /// Move cursor to a character index, clearing any selection.
+    fn set_cursor(&mut self, idx: usize) {
+        self.cursor = self.clamp(idx);
+        self.anchor = None;
     }
 
     /// Save current input and cursor position.
@@ -87,384 +826,3996 @@ impl Inputter {
     fn restore(&mut self) {
         self.cursor = self.saved_cursor;
         self.input = self.saved_input.clone();
+        self.render_placeholder = self.input.is_empty();
+        self.anchor = None;
+        self.scroll = 0;
     }
 
     /// Byte index of current cursor position.
     fn byte_index(&self) -> usize {
+        self.byte_at(self.cursor)
+    }
+
+    /// Byte index of a grapheme index.
+    fn byte_at(&self, idx: usize) -> usize {
         self.input
-            .char_indices()
+            .grapheme_indices(true)
             .map(|(i, _)| i)
-            .nth(self.cursor)
+            .nth(idx)
             .unwrap_or(self.input.len())
     }
 
+    /// Display width of the input up to a grapheme index.
+    fn width_to(&self, idx: usize) -> usize {
+        self.input[..self.byte_at(idx)].width()
+    }
+
+    /// Grapheme index at a display column, counted from the start of the input.
+    fn index_at_width(&self, column: usize) -> usize {
+        let mut width = 0;
+        for (i, g) in self.input.graphemes(true).enumerate() {
+            width += g.width();
+            if width > column {
+                return i;
+            }
+        }
+        self.len()
+    }
+
+    /// Scroll horizontally so the cursor is visible in a box of the given
+    /// width, clear of the ellipsis shown on either side that is clipped.
+    fn scroll_to_cursor(&mut self, width: usize) {
+        let cursor = self.width_to(self.cursor);
+        if width < 3 {
+            self.scroll = cursor;
+            return;
+        }
+        if cursor + 2 > self.scroll + width {
+            self.scroll = cursor + 2 - width;
+        }
+        // Leave no empty space after the text, other than for the cursor.
+        let max_scroll = (self.input.width() + 1).saturating_sub(width);
+        self.scroll = self.scroll.min(max_scroll);
+        if self.scroll > 0 && cursor < self.scroll + 1 {
+            self.scroll = cursor.saturating_sub(1);
+        }
+    }
+
+    /// The visible part of the input in a box of the given width, from the
+    /// current scroll position, with styles applied to the given byte ranges
+    /// and the selection patched with `selected`, and clipped ends marked by
+    /// `clip`, one column wide.
+    fn view(&self, width: usize, styles: &[(Range<usize>, Style)], selected: Style, clip: &Span<'static>) -> Line<'_> {
+        let left_clipped = self.scroll > 0;
+        let right_clipped = self.input.width() > self.scroll + width;
+        let start = self.scroll + usize::from(left_clipped);
+        let end = (self.scroll + width).saturating_sub(usize::from(right_clipped));
+        let selection = self.selection();
+
+        // Runs of graphemes that all have the same style.
+        let mut runs: Vec<(String, Style)> = Vec::new();
+        let mut col = 0;
+        for (i, (byte, g)) in self.input.grapheme_indices(true).enumerate() {
+            let (from, to) = (col, col + g.width());
+            col = to;
+            let mut style = styles.iter()
+                .find(|(range, _)| range.contains(&byte))
+                .map_or(Style::default(), |(_, style)| *style);
+            if selection.is_some_and(|(s, e)| s <= i && i < e) {
+                style = style.patch(selected);
+            }
+            let text = if from >= start && to <= end {
+                g.to_string()
+            } else if from < end && to > start {
+                // A wide grapheme cut off by either edge.
+                " ".repeat(to.min(end) - from.max(start))
+            } else {
+                continue;
+            };
+            match runs.last_mut() {
+                Some((run, s)) if *s == style => run.push_str(&text),
+                _ => runs.push((text, style)),
+            }
+        }
+
+        let mut spans = Vec::new();
+        if left_clipped {
+            spans.push(clip.clone());
+        }
+        for (run, style) in runs {
+            spans.push(Span::styled(run, style));
+        }
+        if right_clipped {
+            spans.push(clip.clone());
+        }
+        Line::from(spans)
+    }
+
+    /// Start and end character indices of the selection, if it is not empty.
+    fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.anchor?;
+        match anchor.cmp(&self.cursor) {
+            std::cmp::Ordering::Less => Some((anchor, self.cursor)),
+            std::cmp::Ordering::Greater => Some((self.cursor, anchor)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    /// Start a selection at the character index, to be extended with `drag_to`.
+    fn select_from(&mut self, idx: usize) {
+        self.set_cursor(idx);
+        self.anchor = Some(self.cursor);
+    }
+
+    /// Move the cursor while keeping the selection anchor in place, starting
+    /// a selection from the cursor if there is none.
+    fn drag_to(&mut self, idx: usize) {
+        if self.anchor.is_none() {
+            self.anchor = Some(self.cursor);
+        }
+        self.cursor = self.clamp(idx);
+    }
+
+    /// The selected text, if any.
+    fn selected_text(&self) -> Option<&str> {
+        let (start, end) = self.selection()?;
+        Some(&self.input[self.byte_at(start)..self.byte_at(end)])
+    }
+
+    /// Select the word, or run of non-word characters, around a grapheme index.
+    fn select_word(&mut self, idx: usize) {
+        let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        if graphemes.is_empty() {
+            return;
+        }
+        let idx = idx.min(graphemes.len() - 1);
+        let class = is_word(graphemes[idx]);
+
+        let mut start = idx;
+        while start > 0 && is_word(graphemes[start - 1]) == class {
+            start -= 1;
+        }
+        let mut end = idx + 1;
+        while end < graphemes.len() && is_word(graphemes[end]) == class {
+            end += 1;
+        }
+        self.anchor = Some(start);
+        self.cursor = end;
+    }
+
+    /// Delete the selected text, if any. Returns whether anything was deleted.
+    fn delete_selection(&mut self) -> bool {
+        let Some((start, end)) = self.selection() else {
+            self.anchor = None;
+            return false;
+        };
+        let range = self.byte_at(start)..self.byte_at(end);
+        self.input.replace_range(range, "");
+        self.cursor = start;
+        self.anchor = None;
+        self.render_placeholder = self.input.is_empty();
+        true
+    }
+
     fn clamp(&mut self, idx: usize) -> usize {
-        idx.clamp(0, self.input.chars().count())
+        idx.clamp(0, self.len())
     }
 
     fn right(&mut self) {
         self.cursor = self.clamp(self.cursor.saturating_add(1));
-        self.render_placeholder = self.cursor == 0;
+        self.render_placeholder = self.input.is_empty();
+        self.anchor = None;
     }
 
     fn left(&mut self) {
         self.cursor = self.clamp(self.cursor.saturating_sub(1));
-        self.render_placeholder = self.cursor == 0;
+        self.render_placeholder = self.input.is_empty();
+        self.anchor = None;
     }
 
     /// Clear input and reset cursor to 0.
     fn reset(&mut self) {
         self.cursor = 0;
         self.render_placeholder = true;
+        self.anchor = None;
+        self.scroll = 0;
         self.input.clear();
     }
 
-    /// Insert a new character at cursor position.
+    /// Insert a new character at cursor position, replacing the selection.
     fn insert(&mut self, c: char) {
+        self.insert_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Insert text at cursor position, replacing the selection.
+    fn insert_str(&mut self, text: &str) {
+        self.delete_selection();
         let index = self.byte_index();
-        self.input.insert(index, c);
-        self.right();
+        self.input.insert_str(index, text);
+        // A combining character joins the grapheme before it, so count
+        // graphemes rather than moving right by one.
+        let end = index + text.len();
+        self.cursor = self.input[..end].graphemes(true).count();
+        self.render_placeholder = self.input.is_empty();
     }
 
     /// Backspace key
     fn delete_left(&mut self) {
-        if self.cursor == 0 {
+        if self.delete_selection() || self.cursor == 0 {
             return;
         }
-        let cur = self.cursor;
         // 0 1 2 3 |4| 5 6
         // 0 1 2   |4| 5 6
-        let before = self.input.chars().take(cur - 1);
-        let after = self.input.chars().skip(cur);
-
-        // Put all characters together except the selected one.
-        // By leaving the selected one out, it is forgotten and therefore deleted.
-        self.input = before.chain(after).collect();
+        let range = self.byte_at(self.cursor - 1)..self.byte_index();
+        self.input.replace_range(range, "");
         self.left();
     }
 
     /// Delete key
     fn delete_right(&mut self) {
-        let cur = self.byte_index();
+        if self.delete_selection() {
+            return;
+        }
         // 0 1 2 3 |4| 5 6
         // 0 1 2 3 |   5 6
-        let before = self.input.chars().take(cur);
-        let after = self.input.chars().skip(cur + 1);
-
-        self.input = before.chain(after).collect();
+        let range = self.byte_index()..self.byte_at(self.cursor + 1);
+        self.input.replace_range(range, "");
+        self.render_placeholder = self.input.is_empty();
     }
-}
-
-// App ////////////////////////////////////////////////////////////////
-#[derive(Debug)]
-struct App {
-    exit: bool,
-    todolist: Todos,
-    focus: Focus,
-    inputter: Inputter,
-    first_todo: bool,
-    editing: Option<usize>,
-}
-
-#[derive(Debug, PartialEq, Default)]
-enum Focus {
-    #[default]
-    Input,
-    List,
-}
 
-impl App {
-    fn new() -> Self {
-        Self {
-            exit: false,
-            todolist: Vec::new(),
-            focus: Focus::Input,
-            inputter: Inputter::new(),
-            first_todo: true,
-            editing: None,
+    /// Index of the start of the word before an index.
+    fn word_start(&self, from: usize, is_word: fn(&str) -> bool) -> usize {
+        let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        let mut i = from.min(graphemes.len());
+        while i > 0 && !is_word(graphemes[i - 1]) {
+            i -= 1;
         }
+        while i > 0 && is_word(graphemes[i - 1]) {
+            i -= 1;
+        }
+        i
     }
 
-    /// Get the border style based on current widget's required focus.
+    /// Index of the end of the word after an index.
+    fn word_end(&self, from: usize) -> usize {
+        let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        let mut i = from;
+        while i < graphemes.len() && !is_word(graphemes[i]) {
+            i += 1;
+        }
+        while i < graphemes.len() && is_word(graphemes[i]) {
+            i += 1;
+        }
+        i
+    }
+
+    /// Index of the start of the next word after an index, like vim's `w`.
+    fn next_word_start(&self, from: usize) -> usize {
+        let graphemes: Vec<&str> = self.input.graphemes(true).collect();
+        let mut i = from;
+        while i < graphemes.len() && is_word(graphemes[i]) {
+            i += 1;
+        }
+        while i < graphemes.len() && !is_word(graphemes[i]) {
+            i += 1;
+        }
+        i
+    }
+
+    /// Alt-b, ctrl-left
+    fn word_left(&mut self) {
+        self.set_cursor(self.word_start(self.cursor, is_word));
+    }
+
+    /// Alt-f, ctrl-right
+    fn word_right(&mut self) {
+        self.set_cursor(self.word_end(self.cursor));
+    }
+
+    /// Start handling a new key or mouse event, so that only kills and
+    /// yanks straight after each other are chained.
+    fn next_command(&mut self) {
+        self.last_kill_state = std::mem::take(&mut self.kill_state);
+    }
+
+    /// Delete text between two grapheme indices and save it to the kill ring,
+    /// joined to the previous entry if the last command was a kill too.
+    fn kill(&mut self, start: usize, end: usize) {
+        if start >= end {
+            return;
+        }
+        let range = self.byte_at(start)..self.byte_at(end);
+        let text = &self.input[range.clone()];
+        match self.kill_ring.last_mut() {
+            // Killing backwards puts the text in front, as it was.
+            Some(last) if self.last_kill_state == KillState::Killed && end == self.cursor => last.insert_str(0, text),
+            Some(last) if self.last_kill_state == KillState::Killed => last.push_str(text),
+            _ => {
+                self.kill_ring.push(text.to_string());
+                if self.kill_ring.len() > KILL_RING_LIMIT {
+                    self.kill_ring.remove(0);
+                }
+            }
+        }
+        self.kill_state = KillState::Killed;
+        self.input.replace_range(range, "");
+        self.set_cursor(start);
+        self.render_placeholder = self.input.is_empty();
+    }
+
+    /// Ctrl-w, kill the whitespace-delimited word before the cursor.
+    fn kill_unix_word_left(&mut self) {
+        self.kill(self.word_start(self.cursor, is_unix_word), self.cursor);
+    }
+
+    /// Alt-backspace
+    fn kill_word_left(&mut self) {
+        self.kill(self.word_start(self.cursor, is_word), self.cursor);
+    }
+
+    /// Alt-d
+    fn kill_word_right(&mut self) {
+        self.kill(self.cursor, self.word_end(self.cursor));
+    }
+
+    /// Ctrl-u
+    fn kill_to_start(&mut self) {
+        self.kill(0, self.cursor);
+    }
+
+    /// Ctrl-k
+    fn kill_to_end(&mut self) {
+        self.kill(self.cursor, self.len());
+    }
+
+    /// Ctrl-y, insert the most recently killed text.
+    fn yank(&mut self) {
+        if let Some(text) = self.kill_ring.last().cloned() {
+            self.delete_selection();
+            let start = self.cursor;
+            self.insert_str(&text);
+            self.kill_state = KillState::Yanked { start, index: self.kill_ring.len() - 1 };
+        }
+    }
+
+    /// Alt-y, straight after a yank, replace the yanked text with the kill
+    /// before it, going round to the most recent after the oldest.
+    fn yank_pop(&mut self) {
+        let KillState::Yanked { start, index } = self.last_kill_state else {
+            return;
+        };
+        let index = index.checked_sub(1).unwrap_or(self.kill_ring.len() - 1);
+        let range = self.byte_at(start)..self.byte_index();
+        self.input.replace_range(range, "");
+        self.set_cursor(start);
+        self.insert_str(&self.kill_ring[index].clone());
+        self.kill_state = KillState::Yanked { start, index };
+    }
+
+    /// Ctrl-t, swap the graphemes on either side of the cursor, or the last
+    /// two when at the end, moving the cursor forward.
+    fn transpose(&mut self) {
+        let len = self.len();
+        if self.cursor == 0 || len < 2 {
+            return;
+        }
+        let at = self.cursor.min(len - 1);
+        let (a, b, c) = (self.byte_at(at - 1), self.byte_at(at), self.byte_at(at + 1));
+        let swapped = format!("{}{}", &self.input[b..c], &self.input[a..b]);
+        self.input.replace_range(a..c, &swapped);
+        self.set_cursor(at + 1);
+    }
+}
+
+// Input history //////////////////////////////////////////////////////
+/// Maximum number of entries kept in the input history.
+const INPUT_HISTORY_LIMIT: usize = 500;
+
+/// Directory for persistent data, following the XDG base directory spec.
+fn data_dir() -> Option<PathBuf> {
+    env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))
+        .map(|dir| dir.join("todomvc-tui"))
+}
+
+/// Previously entered todo names, oldest first, without duplicates.
+#[derive(Debug, Default)]
+struct InputHistory {
+    entries: Vec<String>,
+    /// File the history is persisted to, one entry per line.
+    path: Option<PathBuf>,
+    /// Entry currently recalled with up/down.
+    index: Option<usize>,
+    /// Input from before recalling or searching, to return to.
+    draft: String,
+    /// Query and matching entry of an incremental reverse search.
+    search: Option<(String, Option<usize>)>,
+}
+
+impl InputHistory {
+    /// Load history from a file, if it exists.
+    fn load(path: Option<PathBuf>) -> Self {
+        let mut history = Self::default();
+        if let Some(content) = path.as_ref().and_then(|p| fs::read_to_string(p).ok()) {
+            for line in content.lines().filter(|l| !l.is_empty()) {
+                history.push(line);
+            }
+        }
+        history.path = path;
+        history
+    }
+
+    /// Add an entry as the newest, dropping an older duplicate.
+    fn push(&mut self, entry: &str) {
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+        if self.entries.len() > INPUT_HISTORY_LIMIT {
+            self.entries.remove(0);
+        }
+    }
+
+    /// Add an entry and persist the history. Failing to write is ignored,
+    /// as the history is only a convenience.
+    fn add(&mut self, entry: &str) {
+        self.push(entry);
+        self.index = None;
+        if let Some(path) = &self.path {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            let _ = fs::write(path, self.entries.join("\n") + "\n");
+        }
+    }
+
+    /// Step to the previous entry, saving the current input when starting.
+    fn prev(&mut self, current: &str) -> Option<&str> {
+        let idx = match self.index {
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+            Some(idx) => idx.saturating_sub(1),
+        };
+        self.index = Some(idx);
+        Some(&self.entries[idx])
+    }
+
+    /// Step to the next entry, or back to the saved input past the newest.
+    fn next(&mut self) -> Option<&str> {
+        let idx = self.index? + 1;
+        if idx < self.entries.len() {
+            self.index = Some(idx);
+            Some(&self.entries[idx])
+        } else {
+            self.index = None;
+            Some(&self.draft)
+        }
+    }
+
+    /// Index of the newest entry before `before` that contains the query.
+    fn find(&self, query: &str, before: usize) -> Option<usize> {
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|e| e.contains(query))
+    }
+}
+
+// Clipboard //////////////////////////////////////////////////////////
+/// Standard base64 encoding with padding, as used by OSC 52.
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = u32::from(b[0]) << 16 | u32::from(b[1]) << 8 | u32::from(b[2]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(char::from(TABLE[(n >> (18 - 6 * i)) as usize & 63]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Copied text is kept in an internal register, and also sent to the
+/// system clipboard with the OSC 52 escape sequence, which works over SSH
+/// in terminals that support it.
+#[derive(Debug, Default)]
+struct Clipboard {
+    register: String,
+    /// OSC 52 sequence waiting to be written to the terminal.
+    pending: Option<String>,
+}
+
+impl Clipboard {
+    fn copy(&mut self, text: &str) {
+        self.register = text.to_string();
+        self.pending = Some(format!("\x1b]52;c;{}\x07", base64(text.as_bytes())));
+    }
+}
+
+// Vim ////////////////////////////////////////////////////////////////
+/// Mode of the input when vim bindings are enabled.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Mode {
+    Normal,
+    #[default]
+    Insert,
+    Visual,
+}
+
+/// A change to the todolist that `.` can repeat.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Change {
+    Delete,
+    Put { before: bool },
+    Toggle,
+}
+
+/// State of the opt-in vim bindings.
+#[derive(Debug, Default)]
+struct Vim {
+    enabled: bool,
+    mode: Mode,
+    /// Count typed before a command, like the 5 in `5j`.
+    count: Option<usize>,
+    /// Operator waiting for its second key, like the first d in `dd`.
+    pending: Option<char>,
+    /// Items deleted or yanked, for `p` and `P`.
+    register: Todos,
+    /// The last change and its count, for `.`.
+    last_change: Option<(usize, Change)>,
+}
+
+impl Vim {
+    /// Add a digit to the count, returning whether it was taken as one.
+    /// A leading 0 is not a count, but a motion in the input.
+    fn push_count(&mut self, c: char) -> bool {
+        match c.to_digit(10) {
+            Some(d) if d > 0 || self.count.is_some() => {
+                let d = d as usize;
+                self.count = Some(self.count.unwrap_or(0).saturating_mul(10).saturating_add(d));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Clear the count and pending operator.
+    fn reset(&mut self) {
+        self.count = None;
+        self.pending = None;
+    }
+
+    /// Status shown under the list: the mode, or what has been typed so far.
+    fn status(&self, focus: &Focus) -> String {
+        let typed = self.count.map(|n| n.to_string()).unwrap_or_default()
+            + &self.pending.map(String::from).unwrap_or_default();
+        match (focus, self.mode) {
+            (Focus::Input, Mode::Normal) => format!("-- NORMAL -- {typed}"),
+            (Focus::Input, Mode::Insert) => "-- INSERT --".to_string(),
+            (Focus::Input, Mode::Visual) => format!("-- VISUAL -- {typed}"),
+            _ => typed,
+        }
+    }
+}
+
+// Actions ////////////////////////////////////////////////////////////
+/// Something that can be done by key, and for commands, from the palette.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Action {
+    // Commands
+    Add,
+    Edit,
+    Toggle,
+    Delete,
+    MarkAll,
+    ClearCompleted,
+    Mark,
+    Tag,
+    SetPriority,
+    MoveToList,
+    SwitchList,
+    Sort,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    ClearMarks,
+    Copy,
+    Export,
+    Theme,
+    Board,
+    GroupBoard,
+    MoveCard,
+    Undo,
+    Redo,
+    Help,
+    Quit,
+    // Focus
+    Palette,
+    FocusNext,
+    FocusPrevious,
+    // List
+    Down,
+    Up,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    First,
+    Last,
+    ExtendDown,
+    ExtendUp,
+    ColumnLeft,
+    ColumnRight,
+    // Buttons
+    PreviousButton,
+    NextButton,
+    PressButton,
+    // Input
+    Save,
+    Cancel,
+    LineStart,
+    LineEnd,
+    CharLeft,
+    CharRight,
+    WordLeft,
+    WordRight,
+    DeleteLeft,
+    DeleteRight,
+    KillUnixWord,
+    KillWordLeft,
+    KillWordRight,
+    KillToStart,
+    KillToEnd,
+    Yank,
+    YankPop,
+    Transpose,
+    SelectLeft,
+    SelectRight,
+    SelectToStart,
+    SelectToEnd,
+    SelectWordLeft,
+    SelectWordRight,
+    CopyText,
+    CutText,
+    PasteText,
+    HistoryPrevious,
+    HistoryNext,
+    HistorySearch,
+}
+
+/// Commands listed by the palette, in order.
+const ACTIONS: [Action; 26] = [
+    Action::Add, Action::Edit, Action::Toggle, Action::Delete, Action::MarkAll,
+    Action::ClearCompleted, Action::Mark, Action::Tag, Action::SetPriority,
+    Action::MoveToList, Action::SwitchList, Action::Sort, Action::Search,
+    Action::NextMatch, Action::PreviousMatch, Action::ClearMarks, Action::Copy,
+    Action::Export, Action::Theme, Action::Board, Action::GroupBoard,
+    Action::MoveCard, Action::Undo, Action::Redo, Action::Help, Action::Quit,
+];
+
+/// Where a key binding applies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Context {
+    Global,
+    Input,
+    Buttons,
+    List,
+    /// The list shown as a board, where list keys apply too.
+    Board,
+}
+
+const CONTEXTS: [Context; 5] = [Context::Global, Context::Input, Context::Buttons, Context::List, Context::Board];
+
+impl Context {
+    fn name(self) -> &'static str {
+        match self {
+            Context::Global  => "Anywhere",
+            Context::Input   => "Input",
+            Context::Buttons => "Buttons",
+            Context::List    => "List",
+            Context::Board   => "Board",
+        }
+    }
+
+    /// Contexts whose bindings apply, most specific first.
+    fn chain(self) -> Vec<Context> {
+        match self {
+            Context::Global => vec![Context::Global],
+            Context::Board  => vec![Context::Board, Context::List, Context::Global],
+            context         => vec![context, Context::Global],
+        }
+    }
+}
+
+/// Default key bindings outside of vim mode, see `Keymap`.
+const KEYMAP: &[(Context, KeyModifiers, KeyCode, Action)] = &[
+    (Context::Global,  KeyModifiers::CONTROL, KeyCode::Char('c'), Action::Quit),
+    (Context::Global,  KeyModifiers::NONE,    KeyCode::Tab,       Action::FocusNext),
+    (Context::Global,  KeyModifiers::NONE,    KeyCode::BackTab,   Action::FocusPrevious),
+    (Context::Global,  KeyModifiers::CONTROL, KeyCode::Char('p'), Action::Palette),
+    (Context::Global,  KeyModifiers::CONTROL, KeyCode::Char('z'), Action::Undo),
+    (Context::Global,  KeyModifiers::CONTROL, KeyCode::Char('y'), Action::Redo),
+
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Enter,     Action::Save),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Esc,       Action::Cancel),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Up,        Action::HistoryPrevious),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Down,      Action::HistoryNext),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('r'), Action::HistorySearch),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Home,      Action::LineStart),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('a'), Action::LineStart),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::End,       Action::LineEnd),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('e'), Action::LineEnd),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Left,      Action::CharLeft),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('b'), Action::CharLeft),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Right,     Action::CharRight),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('f'), Action::CharRight),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Left,      Action::WordLeft),
+    (Context::Input,   KeyModifiers::ALT,     KeyCode::Char('b'), Action::WordLeft),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Right,     Action::WordRight),
+    (Context::Input,   KeyModifiers::ALT,     KeyCode::Char('f'), Action::WordRight),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Backspace, Action::DeleteLeft),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('h'), Action::DeleteLeft),
+    (Context::Input,   KeyModifiers::NONE,    KeyCode::Delete,    Action::DeleteRight),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('d'), Action::DeleteRight),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('w'), Action::KillUnixWord),
+    (Context::Input,   KeyModifiers::ALT,     KeyCode::Backspace, Action::KillWordLeft),
+    (Context::Input,   KeyModifiers::ALT,     KeyCode::Char('d'), Action::KillWordRight),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('u'), Action::KillToStart),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('k'), Action::KillToEnd),
+    // Yank isn't on ctrl-y as in readline, as that redoes everywhere.
+    (Context::Input,   KeyModifiers::CONTROL.union(KeyModifiers::ALT), KeyCode::Char('y'), Action::Yank),
+    (Context::Input,   KeyModifiers::ALT,     KeyCode::Char('y'), Action::YankPop),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('t'), Action::Transpose),
+    (Context::Input,   KeyModifiers::SHIFT,   KeyCode::Left,      Action::SelectLeft),
+    (Context::Input,   KeyModifiers::SHIFT,   KeyCode::Right,     Action::SelectRight),
+    (Context::Input,   KeyModifiers::SHIFT,   KeyCode::Home,      Action::SelectToStart),
+    (Context::Input,   KeyModifiers::SHIFT,   KeyCode::End,       Action::SelectToEnd),
+    (Context::Input,   KeyModifiers::SHIFT.union(KeyModifiers::CONTROL), KeyCode::Left,  Action::SelectWordLeft),
+    (Context::Input,   KeyModifiers::SHIFT.union(KeyModifiers::CONTROL), KeyCode::Right, Action::SelectWordRight),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('c'), Action::CopyText),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('x'), Action::CutText),
+    (Context::Input,   KeyModifiers::CONTROL, KeyCode::Char('v'), Action::PasteText),
+
+    (Context::Buttons, KeyModifiers::NONE,    KeyCode::Enter,     Action::PressButton),
+    (Context::Buttons, KeyModifiers::NONE,    KeyCode::Char(' '), Action::PressButton),
+    (Context::Buttons, KeyModifiers::NONE,    KeyCode::Left,      Action::PreviousButton),
+    (Context::Buttons, KeyModifiers::NONE,    KeyCode::Char('h'), Action::PreviousButton),
+    (Context::Buttons, KeyModifiers::NONE,    KeyCode::Right,     Action::NextButton),
+    (Context::Buttons, KeyModifiers::NONE,    KeyCode::Char('l'), Action::NextButton),
+    (Context::Buttons, KeyModifiers::NONE,    KeyCode::Char('?'), Action::Help),
+
+    (Context::Board,   KeyModifiers::NONE,    KeyCode::Char(' '), Action::MoveCard),
+    (Context::Board,   KeyModifiers::NONE,    KeyCode::Enter,     Action::MoveCard),
+    (Context::Board,   KeyModifiers::NONE,    KeyCode::Left,      Action::ColumnLeft),
+    (Context::Board,   KeyModifiers::NONE,    KeyCode::Char('h'), Action::ColumnLeft),
+    (Context::Board,   KeyModifiers::NONE,    KeyCode::Right,     Action::ColumnRight),
+    (Context::Board,   KeyModifiers::NONE,    KeyCode::Char('l'), Action::ColumnRight),
+    (Context::Board,   KeyModifiers::NONE,    KeyCode::Char('t'), Action::GroupBoard),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char(' '), Action::Toggle),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Enter,     Action::Toggle),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('e'), Action::Edit),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('a'), Action::Add),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Backspace, Action::Delete),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Delete,    Action::Delete),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('/'), Action::Search),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('m'), Action::MarkAll),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('c'), Action::ClearCompleted),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('?'), Action::Help),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Down,      Action::Down),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('j'), Action::Down),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Up,        Action::Up),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('k'), Action::Up),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::PageDown,  Action::PageDown),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::PageUp,    Action::PageUp),
+    (Context::List,    KeyModifiers::CONTROL, KeyCode::Char('d'), Action::HalfPageDown),
+    (Context::List,    KeyModifiers::CONTROL, KeyCode::Char('u'), Action::HalfPageUp),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Home,      Action::First),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('g'), Action::First),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::End,       Action::Last),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('G'), Action::Last),
+    (Context::List,    KeyModifiers::SHIFT,   KeyCode::Down,      Action::ExtendDown),
+    (Context::List,    KeyModifiers::SHIFT,   KeyCode::Up,        Action::ExtendUp),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('x'), Action::Mark),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('#'), Action::Tag),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('!'), Action::SetPriority),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('>'), Action::MoveToList),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('L'), Action::SwitchList),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('s'), Action::Sort),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('n'), Action::NextMatch),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('N'), Action::PreviousMatch),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Esc,       Action::ClearMarks),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('y'), Action::Copy),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('X'), Action::Export),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('T'), Action::Theme),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('v'), Action::Board),
+    (Context::List,    KeyModifiers::NONE,    KeyCode::Char('u'), Action::Undo),
+    (Context::List,    KeyModifiers::CONTROL, KeyCode::Char('r'), Action::Redo),
+];
+
+impl Action {
+    fn name(self) -> &'static str {
+        match self {
+            Action::Add             => "Add todo",
+            Action::Edit            => "Edit todo",
+            Action::Toggle          => "Toggle complete",
+            Action::Delete          => "Delete todo",
+            Action::MarkAll         => "Mark all as complete",
+            Action::ClearCompleted  => "Clear completed",
+            Action::Mark            => "Mark for bulk actions",
+            Action::Tag             => "Add tags",
+            Action::SetPriority     => "Set priority",
+            Action::MoveToList      => "Move to list",
+            Action::SwitchList      => "Switch list",
+            Action::Sort            => "Change sort",
+            Action::Search          => "Search",
+            Action::NextMatch       => "Next match",
+            Action::PreviousMatch   => "Previous match",
+            Action::ClearMarks      => "Clear search and marks",
+            Action::Copy            => "Copy todo name",
+            Action::Export          => "Export list as markdown",
+            Action::Theme           => "Switch theme",
+            Action::Board           => "Switch between list and board",
+            Action::GroupBoard      => "Group board by status or tag",
+            Action::MoveCard        => "Move card to next column",
+            Action::Undo            => "Undo",
+            Action::Redo            => "Redo",
+            Action::Help            => "Show key bindings",
+            Action::Quit            => "Quit",
+            Action::Palette         => "Open command palette",
+            Action::FocusNext       => "Focus next",
+            Action::FocusPrevious   => "Focus previous",
+            Action::Down            => "Select next",
+            Action::Up              => "Select previous",
+            Action::PageDown        => "Page down",
+            Action::PageUp          => "Page up",
+            Action::HalfPageDown    => "Half page down",
+            Action::HalfPageUp      => "Half page up",
+            Action::ColumnLeft      => "Select in column to the left",
+            Action::ColumnRight     => "Select in column to the right",
+            Action::First           => "Select first",
+            Action::Last            => "Select last",
+            Action::ExtendDown      => "Mark downwards",
+            Action::ExtendUp        => "Mark upwards",
+            Action::PreviousButton  => "Previous button",
+            Action::NextButton      => "Next button",
+            Action::PressButton     => "Press button",
+            Action::Save            => "Add or save todo",
+            Action::Cancel          => "Cancel, or go to list",
+            Action::LineStart       => "Start of line",
+            Action::LineEnd         => "End of line, or accept suggestion",
+            Action::CharLeft        => "Back a character",
+            Action::CharRight       => "Forward a character, or accept suggestion",
+            Action::WordLeft        => "Back a word",
+            Action::WordRight       => "Forward a word",
+            Action::DeleteLeft      => "Delete before cursor",
+            Action::DeleteRight     => "Delete after cursor",
+            Action::KillUnixWord    => "Kill to whitespace before cursor",
+            Action::KillWordLeft    => "Kill word before cursor",
+            Action::KillWordRight   => "Kill word after cursor",
+            Action::KillToStart     => "Kill to start of line",
+            Action::KillToEnd       => "Kill to end of line",
+            Action::Yank            => "Yank killed text",
+            Action::YankPop         => "Replace yank with earlier kill",
+            Action::Transpose       => "Transpose characters",
+            Action::SelectLeft      => "Select back a character",
+            Action::SelectRight     => "Select forward a character",
+            Action::SelectToStart   => "Select to start of line",
+            Action::SelectToEnd     => "Select to end of line",
+            Action::SelectWordLeft  => "Select back a word",
+            Action::SelectWordRight => "Select forward a word",
+            Action::CopyText        => "Copy selected text",
+            Action::CutText         => "Cut selected text",
+            Action::PasteText       => "Paste",
+            Action::HistoryPrevious => "Previous input from history",
+            Action::HistoryNext     => "Next input from history",
+            Action::HistorySearch   => "Search input history",
+        }
+    }
+
+    /// Short description in the footer, for actions shown there.
+    fn hint(self) -> Option<&'static str> {
+        Some(match self {
+            Action::Quit           => "quit",
+            Action::FocusNext      => "switch focus",
+            Action::Palette        => "commands",
+            Action::Help           => "help",
+            Action::Save           => "save",
+            Action::Cancel         => "cancel",
+            Action::PressButton    => "press",
+            Action::Toggle         => "toggle complete",
+            Action::MoveCard       => "move card",
+            Action::Board          => "board",
+            Action::Edit           => "edit",
+            Action::Add            => "add",
+            Action::Delete         => "delete",
+            Action::Search         => "search",
+            Action::MarkAll        => "mark all",
+            Action::ClearCompleted => "clear completed",
+            _ => return None,
+        })
+    }
+
+    /// Name in the config, like `mark-all`.
+    fn id(self) -> String {
+        let mut id = String::new();
+        for c in format!("{self:?}").chars() {
+            if c.is_uppercase() && !id.is_empty() {
+                id.push('-');
+            }
+            id.push(c.to_ascii_lowercase());
+        }
+        id
+    }
+}
+
+impl Context {
+    /// Name of the context's table in the config, like `[keys.list]`.
+    fn id(self) -> &'static str {
+        match self {
+            Context::Global  => "global",
+            Context::Input   => "input",
+            Context::Buttons => "buttons",
+            Context::List    => "list",
+            Context::Board   => "board",
+        }
+    }
+}
+
+/// A key with its modifiers. Shift is dropped for chars, which are
+/// uppercase already, and for shift-tab.
+type Chord = (KeyModifiers, KeyCode);
+
+fn chord(key: KeyEvent) -> Chord {
+    match key.code {
+        KeyCode::Char(_) | KeyCode::BackTab => (key.modifiers - KeyModifiers::SHIFT, key.code),
+        code => (key.modifiers, code),
+    }
+}
+
+/// Key as shown in hints, like `ctrl-z` or `space`. Parsed back by
+/// `parse_chord`.
+fn key_name((modifiers, code): Chord) -> String {
+    let name = match code {
+        KeyCode::Char(' ') => "space".to_string(),
+        KeyCode::Char(c)   => c.to_string(),
+        KeyCode::BackTab   => "shift-tab".to_string(),
+        KeyCode::F(n)      => format!("f{n}"),
+        code               => format!("{code:?}").to_lowercase(),
+    };
+    let mut prefix = String::new();
+    for (m, text) in [(KeyModifiers::CONTROL, "ctrl-"), (KeyModifiers::ALT, "alt-"), (KeyModifiers::SHIFT, "shift-")] {
+        if modifiers.contains(m) {
+            prefix.push_str(text);
+        }
+    }
+    prefix + &name
+}
+
+/// Key such as `ctrl-x`, `alt-backspace`, `G` or `f2`.
+fn parse_chord(text: &str) -> Option<Chord> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut rest = text;
+    loop {
+        let lower = rest.to_lowercase();
+        let (m, len) = if lower.starts_with("ctrl-") {
+            (KeyModifiers::CONTROL, 5)
+        } else if lower.starts_with("alt-") {
+            (KeyModifiers::ALT, 4)
+        } else if lower.starts_with("shift-") && rest.len() > 6 {
+            (KeyModifiers::SHIFT, 6)
+        } else {
+            break;
+        };
+        modifiers |= m;
+        rest = &rest[len..];
+    }
+    let mut chars = rest.chars();
+    let code = match (chars.next()?, chars.next()) {
+        (c, None) if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_ascii_uppercase()),
+        (c, None) => KeyCode::Char(c),
+        _ => match rest.to_lowercase().as_str() {
+            "space"     => KeyCode::Char(' '),
+            "enter"     => KeyCode::Enter,
+            "esc"       => KeyCode::Esc,
+            "tab"       => KeyCode::Tab,
+            "backtab"   => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete"    => KeyCode::Delete,
+            "insert"    => KeyCode::Insert,
+            "up"        => KeyCode::Up,
+            "down"      => KeyCode::Down,
+            "left"      => KeyCode::Left,
+            "right"     => KeyCode::Right,
+            "home"      => KeyCode::Home,
+            "end"       => KeyCode::End,
+            "pageup"    => KeyCode::PageUp,
+            "pagedown"  => KeyCode::PageDown,
+            f => KeyCode::F(f.strip_prefix('f')?.parse().ok().filter(|n| (1..=24).contains(n))?),
+        },
+    };
+    let code = if code == KeyCode::Tab && modifiers.contains(KeyModifiers::SHIFT) { KeyCode::BackTab } else { code };
+    Some(chord(KeyEvent::new(code, modifiers)))
+}
+
+/// Outcome of looking up the keys typed so far.
+#[derive(Debug, PartialEq)]
+enum Bound {
+    Action(Context, Action),
+    /// The keys start a longer sequence.
+    Pending,
+    Unbound,
+}
+
+/// Key bindings, as sequences of chords for an action in a context.
+/// Bindings in the focused context come before global ones, and the first
+/// binding of an action is the one shown in hints.
+#[derive(Debug, Clone)]
+struct Keymap {
+    bindings: Vec<(Context, Vec<Chord>, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = KEYMAP.iter().map(|&(context, m, code, action)| (context, vec![(m, code)], action)).collect();
+        Self { bindings }
+    }
+}
+
+impl Keymap {
+    /// Action bound to a single key in a context only.
+    fn get(&self, context: Context, key: KeyEvent) -> Option<Action> {
+        let keys = [chord(key)];
+        self.bindings.iter().find(|(c, k, _)| *c == context && *k == keys).map(|(.., a)| *a)
+    }
+
+    /// Look up a key following the keys pending before it, updating them.
+    /// Keys that lead nowhere are dropped.
+    fn resolve(&self, pending: &mut Vec<Chord>, context: Context, key: KeyEvent) -> Bound {
+        pending.push(chord(key));
+        for c in context.chain() {
+            let mut prefix = false;
+            for (_, keys, action) in self.bindings.iter().filter(|(bc, ..)| *bc == c) {
+                if keys == pending {
+                    pending.clear();
+                    return Bound::Action(c, *action);
+                }
+                prefix |= keys.starts_with(pending);
+            }
+            if prefix {
+                return Bound::Pending;
+            }
+        }
+        if pending.len() > 1 {
+            pending.clear();
+            return self.resolve(pending, context, key);
+        }
+        pending.clear();
+        Bound::Unbound
+    }
+
+    /// Keys bound to an action in a context, in order.
+    fn keys(&self, action: Action, context: Context) -> Vec<String> {
+        let mut names: Vec<String> = Vec::new();
+        for (_, keys, _) in self.bindings.iter().filter(|(c, _, a)| *c == context && *a == action) {
+            let name = keys.iter().map(|&k| key_name(k)).collect::<Vec<_>>().join(" ");
+            if !names.contains(&name) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// The key shown for an action, from the list or board if bound there.
+    fn key(&self, action: Action) -> Option<String> {
+        [Context::List, Context::Board, Context::Global, Context::Input, Context::Buttons].into_iter()
+            .find_map(|c| self.keys(action, c).into_iter().next())
+    }
+
+    /// Keys bound to an action in a context of a chain that are not taken
+    /// by a context before it, such as list keys the board uses. Global keys
+    /// are all shown, as quitting works from anywhere.
+    fn reachable_keys(&self, action: Action, context: Context, chain: &[Context]) -> Vec<String> {
+        let before = match context {
+            Context::Global => &[][..],
+            _ => &chain[..chain.iter().position(|&c| c == context).unwrap_or(0)],
+        };
+        let mut names: Vec<String> = Vec::new();
+        for (_, keys, _) in self.bindings.iter().filter(|(c, _, a)| *c == context && *a == action) {
+            let name = keys.iter().map(|&k| key_name(k)).collect::<Vec<_>>().join(" ");
+            if !names.contains(&name) && !self.bindings.iter().any(|(c, k, _)| before.contains(c) && k == keys) {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Footer of key hints for a context, cut short to fit a width.
+    fn footer(&self, context: Context, width: usize, key: Style) -> Line<'static> {
+        let chain = context.chain();
+        let mut actions: Vec<(Action, Vec<String>)> = Vec::new();
+        for (c, _, action) in self.bindings.iter().filter(|(c, ..)| chain.contains(c)) {
+            if action.hint().is_some() && !actions.iter().any(|(a, _)| a == action) {
+                let keys = self.reachable_keys(*action, *c, &chain);
+                if !keys.is_empty() {
+                    actions.push((*action, keys));
+                }
+            }
+        }
+        // Global hints other than quitting go last.
+        let global = |a: &Action| *a != Action::Quit && self.bindings.iter().any(|(c, _, b)| *c == Context::Global && b == a);
+        actions.sort_by_key(|(a, _)| global(a));
+        let mut spans: Vec<Span> = Vec::new();
+        let mut used = 0;
+        for (action, keys) in actions {
+            let keys = keys.join("/");
+            let hint = action.hint().unwrap_or_default();
+            let sep = if spans.is_empty() { "" } else { ", " };
+            let len = sep.len() + keys.width() + 2 + hint.width();
+            if used + len > width {
+                break;
+            }
+            used += len;
+            spans.extend([sep.into(), Span::styled(keys, key), ": ".into(), hint.into()]);
+        }
+        Line::from(spans)
+    }
+
+    /// Replace the keys of actions from a `[keys.<context>]` table of the
+    /// config, where each action maps to a key sequence like `"g g"` or a
+    /// list of them. An empty list unbinds the action.
+    fn apply(&mut self, context: Context, table: &toml::Table, errors: &mut Vec<String>) {
+        let section = format!("[keys.{}]", context.id());
+        let mut actions: Vec<Action> = KEYMAP.iter().map(|(.., a)| *a).collect();
+        actions.extend(ACTIONS);
+        for (id, value) in table {
+            let Some(&action) = actions.iter().find(|a| a.id() == *id) else {
+                errors.push(format!("{section}: unknown action \"{id}\""));
+                continue;
+            };
+            let texts: Vec<&toml::Value> = match value {
+                toml::Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            };
+            let mut sequences = Vec::new();
+            for text in texts {
+                let Some(text) = text.as_str() else {
+                    errors.push(format!("{section}: {id}: expected a key or a list of keys"));
+                    continue;
+                };
+                match text.split_whitespace().map(parse_chord).collect::<Option<Vec<_>>>() {
+                    Some(keys) if !keys.is_empty() => sequences.push(keys),
+                    _ => errors.push(format!("{section}: {id}: unknown key \"{text}\"")),
+                }
+            }
+            self.bindings.retain(|(c, _, a)| !(*c == context && *a == action));
+            self.bindings.extend(sequences.into_iter().map(|keys| (context, keys, action)));
+        }
+    }
+
+    /// Errors for keys bound twice in a context, or that start another
+    /// binding's sequence and so would never be reached. Keys in contexts
+    /// later in a chain, like global ones from the input, count too, unless
+    /// the default keys hide them on purpose, as the board does list keys.
+    fn conflicts(&self) -> Vec<String> {
+        let defaults = Keymap::default();
+        let name = |keys: &[Chord]| keys.iter().map(|&k| key_name(k)).collect::<Vec<_>>().join(" ");
+        let mut errors = Vec::new();
+        for (i, (c, keys, action)) in self.bindings.iter().enumerate() {
+            for (_, other, other_action) in self.bindings[..i].iter().filter(|(oc, ..)| oc == c) {
+                let section = format!("[keys.{}]", c.id());
+                if other == keys && other_action != action {
+                    errors.push(format!(
+                        "{section}: \"{}\" is bound to both {} and {}",
+                        name(keys), other_action.id(), action.id(),
+                    ));
+                } else if other != keys && (other.starts_with(keys) || keys.starts_with(other)) {
+                    let ((short, short_action), (long, long_action)) = if keys.len() < other.len() {
+                        ((keys, action), (other, other_action))
+                    } else {
+                        ((other, other_action), (keys, action))
+                    };
+                    errors.push(format!(
+                        "{section}: \"{}\" for {} starts \"{}\" for {}",
+                        name(short), short_action.id(), name(long), long_action.id(),
+                    ));
+                }
+            }
+            let later = c.chain().into_iter().skip(1);
+            for oc in later {
+                for (_, other, other_action) in self.bindings.iter().filter(|(bc, ..)| *bc == oc) {
+                    let hides = other_action != action && (other.starts_with(keys) || keys.starts_with(other));
+                    let default = |binding: (Context, &Vec<Chord>, Action)| {
+                        defaults.bindings.iter().any(|(dc, dk, da)| (*dc, dk, *da) == binding)
+                    };
+                    if hides && !(default((*c, keys, *action)) && default((oc, other, *other_action))) {
+                        errors.push(format!(
+                            "[keys.{}]: \"{}\" for {} hides \"{}\" for {} in [keys.{}]",
+                            c.id(), name(keys), action.id(), name(other), other_action.id(), oc.id(),
+                        ));
+                    }
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// State of the command palette.
+#[derive(Debug, Default)]
+struct Palette {
+    query: String,
+    selected: usize,
+}
+
+impl Palette {
+    /// Actions matching the query, best first, with the indices of matched
+    /// chars in their names.
+    fn matches(&self) -> Vec<(Action, Vec<usize>)> {
+        let mut found: Vec<(Action, i64, Vec<usize>)> = ACTIONS.iter()
+            .filter_map(|&a| fuzzy_match(&self.query, a.name()).map(|(score, m)| (a, score, m)))
+            .collect();
+        found.sort_by_key(|(_, score, _)| Reverse(*score));
+        found.into_iter().map(|(a, _, m)| (a, m)).collect()
+    }
+}
+
+// Theme //////////////////////////////////////////////////////////////
+/// Every style and glyph the screen is drawn with.
+#[derive(Debug, Clone, PartialEq)]
+struct Theme {
+    name: String,
+    header: String,
+    border: border::Set,
+    /// Border of the focused widget and of popups.
+    focus_border: border::Set,
+    scrollbar: scrollbar::Set,
+    /// Border style of the focused widget and of popups.
+    focus: Style,
+    /// Border style of widgets without focus.
+    border_style: Style,
+    /// Border of the input while it is borrowed, and of confirmations.
+    editing: Style,
+    /// Selected row of the list and of menus.
+    highlight: Style,
+    /// Placeholder, hints and other quiet text.
+    dim: Style,
+    /// Key names in hints and on buttons.
+    key: Style,
+    /// Button without focus.
+    button_idle: Style,
+    /// Button with focus.
+    button: Style,
+    /// Button under the mouse pointer.
+    hover: Style,
+    /// Button held down by the mouse.
+    pressed: Style,
+    /// Text selected in the input.
+    selection: Style,
+    /// Headings of the key binding overlay.
+    heading: Style,
+    /// Chars matching a search.
+    matched: Style,
+    tag: Style,
+    /// Priority being typed in the input.
+    priority: Style,
+    low: Style,
+    medium: Style,
+    high: Style,
+    due: Style,
+    due_today: Style,
+    overdue: Style,
+    recurrence: Style,
+    /// Metadata in the input that is not understood.
+    invalid: Style,
+    scrollbar_style: Style,
+    marker_style: Style,
+    checked: String,
+    unchecked: String,
+    /// In front of marked todos.
+    marker: String,
+    /// In front of how often a todo recurs.
+    repeat: String,
+    /// Next to the current choice in menus.
+    bullet: String,
+    /// Where the input is cut off, one column wide.
+    ellipsis: String,
+}
+
+/// Names of the styles of a theme in the config, in the order of
+/// `Theme::styles_mut`.
+const THEME_STYLES: [&str; 25] = [
+    "focus", "border-style", "editing", "highlight", "dim", "key", "button-idle", "button", "hover", "pressed",
+    "selection", "heading", "matched", "tag", "priority", "low", "medium", "high", "due", "due-today", "overdue",
+    "recurrence", "invalid", "scrollbar-style", "marker-style",
+];
+
+/// Borders made of plain ASCII, for terminals without box drawing.
+const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// ASCII borders that stand out, like double ones.
+const ASCII_DOUBLE_BORDER: border::Set = border::Set {
+    horizontal_top: "=",
+    horizontal_bottom: "=",
+    ..ASCII_BORDER
+};
+
+const ASCII_SCROLLBAR: scrollbar::Set = scrollbar::Set { track: "|", thumb: "#", begin: "^", end: "v" };
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            name: "default".to_string(),
+            header: "T O D O M V C".to_string(),
+            border: border::ROUNDED,
+            focus_border: border::ROUNDED,
+            scrollbar: scrollbar::DOUBLE_VERTICAL,
+            focus: Style::new().blue(),
+            border_style: Style::new(),
+            editing: Style::new().yellow(),
+            highlight: Style::new().white().bg(Color::Rgb(65, 70, 80)),
+            dim: Style::new().dark_gray(),
+            key: Style::new().bold(),
+            button_idle: Style::new(),
+            button: Style::new().black().on_blue(),
+            hover: Style::new().on_dark_gray(),
+            pressed: Style::new().reversed(),
+            selection: Style::new().reversed(),
+            heading: Style::new().bold().underlined(),
+            matched: Style::new().yellow().bold(),
+            tag: Style::new().cyan(),
+            priority: Style::new().yellow(),
+            low: Style::new().blue(),
+            medium: Style::new().yellow(),
+            high: Style::new().red(),
+            due: Style::new().green(),
+            due_today: Style::new().yellow(),
+            overdue: Style::new().red(),
+            recurrence: Style::new().magenta(),
+            invalid: Style::new().red().underlined(),
+            scrollbar_style: Style::new(),
+            marker_style: Style::new().bold(),
+            checked: "(X)".to_string(),
+            unchecked: "( )".to_string(),
+            marker: "▸".to_string(),
+            repeat: "↻".to_string(),
+            bullet: "•".to_string(),
+            ellipsis: "…".to_string(),
+        }
+    }
+}
+
+impl Theme {
+    /// Themes to pick from, starting with the default.
+    fn builtin() -> Vec<Theme> {
+        let default = Theme::default();
+        let light = Theme {
+            name: "light".to_string(),
+            focus: Style::new().fg(Color::Rgb(40, 90, 200)),
+            border_style: Style::new().fg(Color::Rgb(170, 170, 170)),
+            editing: Style::new().fg(Color::Rgb(190, 120, 0)),
+            highlight: Style::new().black().bg(Color::Rgb(210, 215, 225)),
+            dim: Style::new().fg(Color::Rgb(130, 130, 130)),
+            button: Style::new().white().bg(Color::Rgb(40, 90, 200)),
+            hover: Style::new().bg(Color::Rgb(225, 225, 225)),
+            matched: Style::new().fg(Color::Rgb(190, 50, 120)).bold(),
+            tag: Style::new().fg(Color::Rgb(0, 130, 150)),
+            priority: Style::new().fg(Color::Rgb(190, 120, 0)),
+            low: Style::new().fg(Color::Rgb(40, 90, 200)),
+            medium: Style::new().fg(Color::Rgb(190, 120, 0)),
+            high: Style::new().fg(Color::Rgb(200, 40, 40)),
+            due: Style::new().fg(Color::Rgb(30, 130, 50)),
+            due_today: Style::new().fg(Color::Rgb(190, 120, 0)),
+            overdue: Style::new().fg(Color::Rgb(200, 40, 40)),
+            recurrence: Style::new().fg(Color::Rgb(140, 60, 170)),
+            invalid: Style::new().fg(Color::Rgb(200, 40, 40)).underlined(),
+            ..default.clone()
+        };
+        let dusk = Theme {
+            name: "dusk".to_string(),
+            border: border::THICK,
+            focus_border: border::THICK,
+            focus: Style::new().fg(Color::Rgb(136, 192, 208)),
+            border_style: Style::new().fg(Color::Rgb(76, 86, 106)),
+            editing: Style::new().fg(Color::Rgb(235, 203, 139)),
+            highlight: Style::new().fg(Color::Rgb(236, 239, 244)).bg(Color::Rgb(67, 76, 94)),
+            dim: Style::new().fg(Color::Rgb(97, 110, 136)),
+            button: Style::new().fg(Color::Rgb(46, 52, 64)).bg(Color::Rgb(136, 192, 208)),
+            hover: Style::new().bg(Color::Rgb(59, 66, 82)),
+            matched: Style::new().fg(Color::Rgb(235, 203, 139)).bold(),
+            tag: Style::new().fg(Color::Rgb(143, 188, 187)),
+            priority: Style::new().fg(Color::Rgb(208, 135, 112)),
+            low: Style::new().fg(Color::Rgb(129, 161, 193)),
+            medium: Style::new().fg(Color::Rgb(235, 203, 139)),
+            high: Style::new().fg(Color::Rgb(191, 97, 106)),
+            due: Style::new().fg(Color::Rgb(163, 190, 140)),
+            due_today: Style::new().fg(Color::Rgb(235, 203, 139)),
+            overdue: Style::new().fg(Color::Rgb(191, 97, 106)),
+            recurrence: Style::new().fg(Color::Rgb(180, 142, 173)),
+            invalid: Style::new().fg(Color::Rgb(191, 97, 106)).underlined(),
+            marker: "•".to_string(),
+            ..default.clone()
+        };
+        let classic = Theme {
+            name: "classic".to_string(),
+            header: "todos".to_string(),
+            border: border::PLAIN,
+            focus_border: border::PLAIN,
+            focus: Style::new().bold(),
+            editing: Style::new().bold().italic(),
+            highlight: Style::new().reversed(),
+            button: Style::new().reversed(),
+            hover: Style::new().underlined(),
+            matched: Style::new().bold().underlined(),
+            checked: "[x]".to_string(),
+            unchecked: "[ ]".to_string(),
+            marker: ">".to_string(),
+            ..default.clone()
+        };
+        vec![default, light, dusk, classic]
+    }
+
+    /// Without colors, with focus shown by bold text and double borders
+    /// instead, and the selection by reversing it.
+    fn monochrome(mut self) -> Theme {
+        for style in self.styles_mut() {
+            *style = Style { fg: None, bg: None, underline_color: None, ..*style };
+        }
+        Theme {
+            focus_border: if self.border == ASCII_BORDER { ASCII_DOUBLE_BORDER } else { border::DOUBLE },
+            focus: Style::new().bold(),
+            editing: Style::new().bold().italic(),
+            highlight: Style::new().reversed(),
+            button: Style::new().reversed(),
+            hover: Style::new().underlined(),
+            matched: Style::new().bold().underlined(),
+            dim: Style::new().dim(),
+            overdue: Style::new().bold(),
+            high: Style::new().bold(),
+            ..self
+        }
+    }
+
+    /// Drawn only with ASCII, for serial consoles and minimal terminals.
+    fn ascii(self) -> Theme {
+        let ascii = |text: String, fallback: &str| if text.is_ascii() { text } else { fallback.to_string() };
+        let (checked, unchecked) = if self.checked.is_ascii() && self.unchecked.is_ascii() {
+            (self.checked, self.unchecked)
+        } else {
+            ("[x]".to_string(), "[ ]".to_string())
+        };
+        Theme {
+            header: ascii(self.header, "TODOMVC"),
+            focus_border: if self.focus_border == self.border { ASCII_BORDER } else { ASCII_DOUBLE_BORDER },
+            border: ASCII_BORDER,
+            scrollbar: ASCII_SCROLLBAR,
+            checked,
+            unchecked,
+            marker: ascii(self.marker, ">"),
+            repeat: ascii(self.repeat, "every"),
+            bullet: ascii(self.bullet, "*"),
+            ellipsis: ascii(self.ellipsis, "~"),
+            ..self
+        }
+    }
+
+    /// With colors the terminal cannot show swapped for the nearest it can.
+    fn downsample(mut self, support: ColorSupport) -> Theme {
+        for style in self.styles_mut() {
+            style.fg = style.fg.map(|c| support.color(c));
+            style.bg = style.bg.map(|c| support.color(c));
+            style.underline_color = style.underline_color.map(|c| support.color(c));
+        }
+        self
+    }
+
+    fn styles_mut(&mut self) -> [&mut Style; THEME_STYLES.len()] {
+        [
+            &mut self.focus, &mut self.border_style, &mut self.editing, &mut self.highlight, &mut self.dim,
+            &mut self.key, &mut self.button_idle, &mut self.button, &mut self.hover, &mut self.pressed,
+            &mut self.selection, &mut self.heading, &mut self.matched, &mut self.tag, &mut self.priority,
+            &mut self.low, &mut self.medium, &mut self.high, &mut self.due, &mut self.due_today,
+            &mut self.overdue, &mut self.recurrence, &mut self.invalid, &mut self.scrollbar_style,
+            &mut self.marker_style,
+        ]
+    }
+
+    fn priority_style(&self, priority: Priority) -> Style {
+        match priority {
+            Priority::Low    => self.low,
+            Priority::Medium => self.medium,
+            Priority::High   => self.high,
+        }
+    }
+
+    /// Columns the checkbox of a row takes up, after the marker.
+    fn checkbox_columns(&self) -> Range<u16> {
+        let start = self.marker.width() + 2;
+        let end = start + self.checked.width().max(self.unchecked.width());
+        u16::try_from(start).unwrap_or(0)..u16::try_from(end).unwrap_or(0)
+    }
+
+    /// Change a setting from a `[themes.<name>]` table of the config.
+    fn set(&mut self, key: &str, value: &str) -> std::result::Result<(), String> {
+        let text = match key {
+            "header"    => Some(&mut self.header),
+            "checked"   => Some(&mut self.checked),
+            "unchecked" => Some(&mut self.unchecked),
+            "marker"    => Some(&mut self.marker),
+            "repeat"    => Some(&mut self.repeat),
+            "bullet"    => Some(&mut self.bullet),
+            _ => None,
+        };
+        if let Some(text) = text {
+            *text = value.to_string();
+            return Ok(());
+        }
+        let set = match value {
+            "plain"   => Some(border::PLAIN),
+            "rounded" => Some(border::ROUNDED),
+            "double"  => Some(border::DOUBLE),
+            "thick"   => Some(border::THICK),
+            "ascii"   => Some(ASCII_BORDER),
+            _ => None,
+        };
+        match key {
+            "border" | "focus-border" => {
+                let set = set.ok_or_else(|| {
+                    format!("{key}: unknown border \"{value}\", expected plain, rounded, double, thick or ascii")
+                })?;
+                if key == "border" {
+                    self.border = set;
+                }
+                self.focus_border = set;
+            }
+            "ellipsis" if value.width() == 1 => self.ellipsis = value.to_string(),
+            "ellipsis" => return Err(format!("ellipsis: expected one column, not \"{value}\"")),
+            _ => {
+                let i = THEME_STYLES.iter().position(|name| *name == key)
+                    .ok_or_else(|| format!("unknown setting \"{key}\""))?;
+                *self.styles_mut()[i] = parse_style(value).ok_or_else(|| format!("{key}: unknown style \"{value}\""))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Style such as `bold yellow`, `white on #41464f` or `underlined 208`.
+fn parse_style(text: &str) -> Option<Style> {
+    let mut style = Style::new();
+    let mut words = text.split_whitespace();
+    while let Some(word) = words.next() {
+        style = match word {
+            "default"    => style,
+            "bold"       => style.bold(),
+            "dim"        => style.dim(),
+            "italic"     => style.italic(),
+            "underlined" => style.underlined(),
+            "reversed"   => style.reversed(),
+            "on"         => style.bg(words.next()?.parse().ok()?),
+            color        => style.fg(color.parse().ok()?),
+        };
+    }
+    Some(style)
+}
+
+// Terminal colors ////////////////////////////////////////////////////
+/// Colors a terminal can show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorSupport {
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+/// The 16 ANSI colors, as xterm draws them.
+const ANSI_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black,        (0, 0, 0)),
+    (Color::Red,          (205, 0, 0)),
+    (Color::Green,        (0, 205, 0)),
+    (Color::Yellow,       (205, 205, 0)),
+    (Color::Blue,         (0, 0, 238)),
+    (Color::Magenta,      (205, 0, 205)),
+    (Color::Cyan,         (0, 205, 205)),
+    (Color::Gray,         (229, 229, 229)),
+    (Color::DarkGray,     (127, 127, 127)),
+    (Color::LightRed,     (255, 0, 0)),
+    (Color::LightGreen,   (0, 255, 0)),
+    (Color::LightYellow,  (255, 255, 0)),
+    (Color::LightBlue,    (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan,    (0, 255, 255)),
+    (Color::White,        (255, 255, 255)),
+];
+
+/// Levels of each channel in the 6x6x6 cube of the 256 color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// RGB of a color of the 256 color palette.
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI_COLORS[usize::from(i)].1,
+        16..=231 => {
+            let i = usize::from(i - 16);
+            (CUBE_LEVELS[i / 36], CUBE_LEVELS[i / 6 % 6], CUBE_LEVELS[i % 6])
+        }
+        _ => {
+            let level = 8 + 10 * (i - 232);
+            (level, level, level)
+        }
+    }
+}
+
+/// Nearest color of the 256 color palette, leaving out the first 16 as
+/// terminals often change them.
+fn nearest_indexed(rgb: (u8, u8, u8)) -> u8 {
+    (16..=255).min_by_key(|&i| distance(rgb, indexed_rgb(i))).unwrap_or(16)
+}
+
+/// Nearest of the 16 ANSI colors. Plain distance would turn muted colors
+/// gray, so only grays are matched by it, and other colors by which of their
+/// channels stand out.
+fn nearest_ansi((r, g, b): (u8, u8, u8)) -> Color {
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    if max - min < 48 {
+        let grays = [0, 7, 8, 15].map(|i| ANSI_COLORS[i]);
+        return grays.iter().min_by_key(|(_, gray)| distance((r, g, b), *gray)).map_or(Color::Reset, |(color, _)| *color);
+    }
+    let middle = (u16::from(max) + u16::from(min)) / 2;
+    let on = |c: u8| usize::from(u16::from(c) > middle);
+    let i = on(r) + 2 * on(g) + 4 * on(b) + if max > 230 { 8 } else { 0 };
+    ANSI_COLORS[i].0
+}
+
+impl ColorSupport {
+    /// Guess from `COLORTERM` and `TERM`, as terminals cannot be asked.
+    fn detect(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if colorterm.is_some_and(|c| c == "truecolor" || c == "24bit") || term.is_some_and(|t| t.ends_with("-direct")) {
+            ColorSupport::TrueColor
+        } else if term.is_some_and(|t| t.contains("256color")) {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+
+    /// The color itself if it can be shown, otherwise the nearest that can.
+    fn color(self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::TrueColor, _) => color,
+            (ColorSupport::Ansi256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_indexed((r, g, b))),
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => nearest_ansi((r, g, b)),
+            (ColorSupport::Ansi16, Color::Indexed(i)) => nearest_ansi(indexed_rgb(i)),
+            _ => color,
+        }
+    }
+}
+
+/// Background color from an OSC 11 reply, like `ESC ] 11;rgb:1e1e/1e1e/2e2e ESC \`,
+/// where each channel has one to four hex digits.
+fn parse_background(reply: &str) -> Option<(u8, u8, u8)> {
+    let start = reply.find("]11;rgb:")? + 8;
+    let rest = &reply[start..];
+    let end = rest.find(['\x07', '\x1b']).unwrap_or(rest.len());
+    let channel = |hex: &str| {
+        let value = u32::from_str_radix(hex, 16).ok().filter(|_| (1..=4).contains(&hex.len()))?;
+        let max = 16u32.pow(u32::try_from(hex.len()).ok()?) - 1;
+        u8::try_from(value * 255 / max).ok()
+    };
+    let mut channels = rest[..end].split('/').map(channel);
+    let rgb = (channels.next()??, channels.next()??, channels.next()??);
+    channels.next().is_none().then_some(rgb)
+}
+
+/// Whether `COLORFGBG`, like `15;0`, gives a light background.
+fn colorfgbg_is_light(value: &str) -> Option<bool> {
+    let background: u8 = value.rsplit(';').next()?.parse().ok()?;
+    Some(matches!(background, 7 | 9..=15))
+}
+
+fn is_light((r, g, b): (u8, u8, u8)) -> bool {
+    299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b) > 128_000
+}
+
+/// Bytes read from the tty while waiting for the terminal's replies, sorted
+/// into escape sequences and what was typed in the meantime. The replies end
+/// with device attributes, `ESC [ ? ... c`, which every terminal sends.
+#[derive(Debug, Default)]
+struct Replies {
+    replies: Vec<u8>,
+    sequence: Vec<u8>,
+    typed: Vec<u8>,
+    done: bool,
+}
+
+impl Replies {
+    /// Take bytes as they were read, however the reads split them, and
+    /// return whether the replies are complete.
+    fn feed(&mut self, bytes: &[u8]) -> bool {
+        for &byte in bytes {
+            if self.done || (self.sequence.is_empty() && byte != 0x1b) {
+                self.typed.push(byte);
+                continue;
+            }
+            self.sequence.push(byte);
+            let ended = match self.sequence[1..] {
+                [] => false,
+                // OSC ends with BEL or `ESC \`, and CSI with a final byte.
+                [b']', ..] => byte == 0x07 || self.sequence.ends_with(b"\x1b\\"),
+                [b'[', _, ..] => (0x40..=0x7e).contains(&byte),
+                [b'[', ..] => false,
+                // Anything else is a key typed with alt.
+                _ => {
+                    self.typed.append(&mut self.sequence);
+                    continue;
+                }
+            };
+            if ended {
+                self.done = self.sequence.starts_with(b"\x1b[?") && byte == b'c';
+                self.replies.append(&mut self.sequence);
+            }
+        }
+        self.done
+    }
+
+    fn background(&self) -> Option<(u8, u8, u8)> {
+        parse_background(&String::from_utf8_lossy(&self.replies))
+    }
+
+    /// Keys typed while waiting, as crossterm would have read them. Keys
+    /// sent as escape sequences, like arrows, are dropped with the replies.
+    fn keys(&self) -> Vec<KeyEvent> {
+        let text = String::from_utf8_lossy(&self.typed);
+        let mut chars = text.chars().peekable();
+        let mut keys = Vec::new();
+        while let Some(c) = chars.next() {
+            let alt = c == '\x1b' && chars.peek().is_some();
+            let c = if alt { chars.next().unwrap_or(c) } else { c };
+            let (code, modifiers) = match c {
+                '\r' | '\n'       => (KeyCode::Enter, KeyModifiers::NONE),
+                '\t'              => (KeyCode::Tab, KeyModifiers::NONE),
+                '\x7f' | '\x08'   => (KeyCode::Backspace, KeyModifiers::NONE),
+                '\x1b'            => (KeyCode::Esc, KeyModifiers::NONE),
+                '\x01'..='\x1a'   => (KeyCode::Char(char::from(c as u8 + b'a' - 1)), KeyModifiers::CONTROL),
+                c if c.is_control() => continue,
+                c if c.is_uppercase() => (KeyCode::Char(c), KeyModifiers::SHIFT),
+                c                  => (KeyCode::Char(c), KeyModifiers::NONE),
+            };
+            let modifiers = if alt { modifiers | KeyModifiers::ALT } else { modifiers };
+            keys.push(KeyEvent::new(code, modifiers));
+        }
+        keys
+    }
+}
+
+/// Ask the terminal for its background color, in raw mode. Device
+/// attributes are asked for after it, which every terminal answers, so the
+/// reply ends even when OSC 11 is not understood. The color is only waited
+/// for until the timeout. A reply later than that is still read and thrown
+/// away for a while, rather than left for the app to take as keys. Keys
+/// typed meanwhile are returned with the color, for the app to replay.
+#[cfg(unix)]
+fn query_background(timeout: Duration) -> (Option<(u8, u8, u8)>, Vec<KeyEvent>) {
+    use std::os::fd::AsRawFd;
+
+    /// How long to keep reading a reply that missed the timeout.
+    const DRAIN: Duration = Duration::from_secs(1);
+
+    let Ok(mut tty) = fs::OpenOptions::new().read(true).write(true).open("/dev/tty") else {
+        return (None, Vec::new());
+    };
+    if tty.write_all(b"\x1b]11;?\x1b\\\x1b[c").and_then(|()| tty.flush()).is_err() {
+        return (None, Vec::new());
+    }
+    let start = Instant::now();
+    let mut replies = Replies::default();
+    let mut buf = [0; 64];
+    loop {
+        let left = (start + timeout + DRAIN).saturating_duration_since(Instant::now());
+        let mut poll = libc::pollfd { fd: tty.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        let millis = libc::c_int::try_from(left.as_millis()).unwrap_or(libc::c_int::MAX);
+        // SAFETY: `poll` is one pollfd for the tty, which is open until the
+        // end of the function.
+        if left.is_zero() || unsafe { libc::poll(&mut poll, 1, millis) } <= 0 {
+            break;
+        }
+        match tty.read(&mut buf) {
+            Ok(n) if n > 0 => {
+                if replies.feed(&buf[..n]) {
+                    break;
+                }
+            }
+            _ => break,
+        }
+    }
+    let background = (replies.done && start.elapsed() <= timeout).then(|| replies.background()).flatten();
+    (background, replies.keys())
+}
+
+/// Only terminals on Unix are asked.
+#[cfg(not(unix))]
+fn query_background(_timeout: Duration) -> (Option<(u8, u8, u8)>, Vec<KeyEvent>) {
+    (None, Vec::new())
+}
+
+/// Whether the terminal background is light, from the color the terminal
+/// gave or else `COLORFGBG`. Dark is assumed when neither tells.
+fn light_background(background: Option<(u8, u8, u8)>) -> bool {
+    background.map(is_light)
+        .or_else(|| env::var("COLORFGBG").ok().as_deref().and_then(colorfgbg_is_light))
+        .unwrap_or(false)
+}
+
+// Config /////////////////////////////////////////////////////////////
+fn config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("todomvc-tui"))
+}
+
+/// Settings from `config.toml`, such as:
+///
+/// ```toml
+/// theme = "mine"
+///
+/// [keys.list]
+/// down = "down"
+/// up = ["up", "ctrl-k"]
+/// first = "g g"
+///
+/// [themes.mine]
+/// base = "light"
+/// focus = "bold magenta"
+/// checked = "[x]"
+/// ```
+#[derive(Debug)]
+struct Config {
+    keymap: Keymap,
+    /// Built-in themes, then those of the config.
+    themes: Vec<Theme>,
+    /// The theme to start with, otherwise one to suit the background.
+    theme: Option<Theme>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self { keymap: Keymap::default(), themes: Theme::builtin(), theme: None }
+    }
+}
+
+impl Config {
+    /// Load the config from a file, if it exists, with every problem found.
+    /// A file that can't be read is a problem too.
+    fn load(path: Option<PathBuf>) -> std::result::Result<Self, Vec<String>> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(vec![e.to_string()]),
+        }
+    }
+
+    fn parse(text: &str) -> std::result::Result<Self, Vec<String>> {
+        // Syntax errors say where they are, with the line quoted.
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| vec![e.to_string().trim_end().to_string()])?;
+        let mut config = Self::default();
+        let mut errors = Vec::new();
+        for (name, value) in &table {
+            match (name.as_str(), value) {
+                ("keys", toml::Value::Table(contexts)) => {
+                    for (id, value) in contexts {
+                        let context = CONTEXTS.into_iter().find(|c| c.id() == id);
+                        match (context, value) {
+                            (Some(context), toml::Value::Table(keys)) => config.keymap.apply(context, keys, &mut errors),
+                            (Some(_), _) => errors.push(format!("[keys.{id}]: expected a table")),
+                            (None, _) => errors.push(format!(
+                                "unknown section [keys.{id}], expected one of: {}",
+                                CONTEXTS.map(Context::id).join(", "),
+                            )),
+                        }
+                    }
+                }
+                ("keys", _) => errors.push("[keys]: expected a table".to_string()),
+                ("themes", toml::Value::Table(themes)) => {
+                    for (name, value) in themes {
+                        match value {
+                            toml::Value::Table(settings) => config.add_theme(name, settings, &mut errors),
+                            _ => errors.push(format!("[themes.{name}]: expected a table")),
+                        }
+                    }
+                }
+                ("themes", _) => errors.push("[themes]: expected a table".to_string()),
+                ("theme", _) => {}
+                _ => errors.push(format!("unknown setting \"{name}\"")),
+            }
+        }
+        if let Some(value) = table.get("theme") {
+            match value.as_str().and_then(|name| config.themes.iter().find(|t| t.name == name)) {
+                Some(theme) => config.theme = Some(theme.clone()),
+                None => errors.push(format!(
+                    "theme: expected one of: {}",
+                    config.themes.iter().map(|t| t.name.as_str()).collect::<Vec<_>>().join(", "),
+                )),
+            }
+        }
+        errors.extend(config.keymap.conflicts());
+        if errors.is_empty() { Ok(config) } else { Err(errors) }
+    }
+
+    /// Add a theme made of a built-in one, `base`, with some settings changed.
+    fn add_theme(&mut self, name: &str, settings: &toml::Table, errors: &mut Vec<String>) {
+        let section = format!("[themes.{name}]");
+        let base = settings.get("base").map_or(Some("default"), toml::Value::as_str);
+        let Some(mut theme) = Theme::builtin().into_iter().find(|t| Some(t.name.as_str()) == base) else {
+            let names: Vec<String> = Theme::builtin().into_iter().map(|t| t.name).collect();
+            return errors.push(format!("{section}: base: expected one of: {}", names.join(", ")));
+        };
+        theme.name = name.to_string();
+        for (key, value) in settings.iter().filter(|(key, _)| *key != "base") {
+            let result = value.as_str()
+                .ok_or_else(|| format!("{key}: expected a string"))
+                .and_then(|value| theme.set(key, value));
+            if let Err(error) = result {
+                errors.push(format!("{section}: {error}"));
+            }
+        }
+        self.themes.retain(|t| t.name != name);
+        self.themes.push(theme);
+    }
+}
+
+// Layout /////////////////////////////////////////////////////////////
+/// Key and label of the buttons between the input and the list.
+const BUTTONS: [(&str, &str); 2] = [("(M)", "Mark all as complete"), ("(C)", "Clear completed")];
+
+/// Screen areas of each widget, shared by drawing and mouse hit-testing.
+#[derive(Debug, Default, Clone, Copy)]
+struct Areas {
+    header: Rect,
+    input: Rect,
+    actions: Rect,
+    buttons: [Rect; BUTTONS.len()],
+    list: Rect,
+    itemsleft: Rect,
+    footer: Rect,
+}
+
+/// Width the main section keeps before its margins start to shrink.
+const MAIN_MIN_WIDTH: u16 = 40;
+
+impl Areas {
+    fn new(full: Rect) -> Self {
+        // Margins shrink on narrow terminals, and areas that don't fit are
+        // cut short rather than drawn off screen.
+        let margin_side = 30.min(full.width.saturating_sub(MAIN_MIN_WIDTH) / 2);
+        let list_top = 13;
+        let list_bot = 4;
+        let width = full.width - margin_side - margin_side;
+        let clip = |area: Rect| area.intersection(full);
+        let actions = clip(Rect::new(margin_side, list_top - 1, width, 1));
+
+        let mut buttons = [Rect::default(); BUTTONS.len()];
+        let mut x = margin_side;
+        for (area, (key, label)) in buttons.iter_mut().zip(BUTTONS) {
+            // Padded with a space on either side.
+            let w = u16::try_from(key.len() + label.len() + 3).unwrap();
+            *area = Rect::new(x, list_top - 1, w, 1).intersection(actions);
+            x += w + 1;
+        }
+        // Left out rather than drawn over the input when there's no room.
+        let itemsleft = match full.height.checked_sub(list_bot) {
+            Some(y) if y >= list_top => Rect::new(margin_side, y, width, 1),
+            _ => Rect::default(),
+        };
+
+        Self {
+            header: clip(Rect::new(0, 5, full.width.saturating_sub(1), 1)),
+            input: clip(Rect::new(margin_side, 9, width, 3)),
+            actions,
+            buttons,
+            list: clip(Rect::new(margin_side, list_top, width, full.height.saturating_sub(list_top + list_bot))),
+            itemsleft,
+            footer: clip(Rect::new(0, full.height.saturating_sub(1), full.width.saturating_sub(1), 1)),
+        }
+    }
+
+    /// Width available for text inside the input's borders and padding.
+    fn input_text_width(&self) -> usize {
+        usize::from(self.input.width.saturating_sub(4))
+    }
+
+    /// Area inside the list borders where the items are drawn.
+    fn list_inner(&self) -> Rect {
+        self.list.inner(&Margin::new(1, 1))
+    }
+
+    /// Number of items that fit in the list at once.
+    fn list_page(&self) -> usize {
+        usize::from(self.list_inner().height / 3).max(1)
+    }
+}
+
+/// Whether the given cell lies within a rect.
+fn hit(area: Rect, column: u16, row: u16) -> bool {
+    column >= area.x && column < area.right() && row >= area.y && row < area.bottom()
+}
+
+/// Narrowest a column of the board is drawn.
+const BOARD_COLUMN_WIDTH: u16 = 16;
+
+/// Areas of the board's columns that fit, scrolled to show the selected
+/// column.
+fn board_layout(area: Rect, columns: usize, selected: Option<usize>) -> Vec<(usize, Rect)> {
+    let fit = usize::from(area.width / BOARD_COLUMN_WIDTH).clamp(1, columns.max(1));
+    let first = selected.unwrap_or(0).saturating_sub(fit - 1).min(columns.saturating_sub(fit));
+    let shown = fit.min(columns);
+    let width = area.width / u16::try_from(shown.max(1)).unwrap_or(1);
+    (0..shown).map(|n| {
+        let x = area.x + width * u16::try_from(n).unwrap_or(0);
+        let w = if n + 1 == shown { area.right() - x } else { width };
+        (first + n, Rect { x, width: w, ..area })
+    }).collect()
+}
+
+/// Column and row of the selected todo on the board.
+fn card_position(columns: &[(String, Vec<usize>)], state: &ListState) -> Option<(usize, usize)> {
+    let sel = state.selected()?;
+    columns.iter().enumerate().find_map(|(c, (_, items))| Some((c, items.iter().position(|&i| i == sel)?)))
+}
+
+// App ////////////////////////////////////////////////////////////////
+#[derive(Debug)]
+struct App {
+    exit: bool,
+    todolist: Todos,
+    focus: Focus,
+    inputter: Inputter,
+    first_todo: bool,
+    editing: Option<usize>,
+    /// Areas of the most recently drawn frame.
+    areas: Areas,
+    /// Time and position of the last click in the input, to detect double-clicks.
+    last_click: Option<(Instant, u16, u16)>,
+    /// Whether a click-drag selection in the input is in progress.
+    dragging: bool,
+    /// Index of the button that has focus when `Focus::Buttons`.
+    button: usize,
+    /// Button under the mouse pointer.
+    hover: Option<usize>,
+    /// Button that the mouse is held down on.
+    pressed: Option<usize>,
+    history: History,
+    clipboard: Clipboard,
+    input_history: InputHistory,
+    /// Todos from a multi-line paste, waiting to be confirmed.
+    pending_paste: Option<Todos>,
+    vim: Vim,
+    /// Where the next new todo is inserted, instead of at the end.
+    insert_at: Option<usize>,
+    completion: Completion,
+    search: Option<Search>,
+    sort: Sort,
+    sort_modes: SortModes,
+    /// Compares names for alphabetical sorting, see `collator`.
+    collator: Collator,
+    /// Highlighted entry of the sort menu, while it is open.
+    sort_menu: Option<usize>,
+    palette: Option<Palette>,
+    /// Scroll of the key bindings overlay, while it is open.
+    help: Option<u16>,
+    keymap: Keymap,
+    /// Keys typed so far of a multi-key binding.
+    pending_keys: Vec<Chord>,
+    /// Name of the list shown.
+    list: String,
+    /// The other lists, in the order they were made.
+    lists: Vec<(String, Todos)>,
+    /// What the input is borrowed for, besides editing.
+    prompt: Option<Prompt>,
+    theme: Theme,
+    /// Themes to switch between.
+    themes: Vec<Theme>,
+    /// Whether the todos are shown as a board instead of a list.
+    board: bool,
+    grouping: Grouping,
+}
+
+/// How the board puts todos in columns.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+enum Grouping {
+    /// Todo, In progress and Done.
+    #[default]
+    Status,
+    /// A column for each tag, by the first tag of each todo.
+    Tags,
+}
+
+/// Prompts in the input for acting on the marked todos or switching lists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Prompt {
+    Tag,
+    Priority,
+    Move,
+    Switch,
+}
+
+impl Prompt {
+    fn title(self) -> &'static str {
+        match self {
+            Prompt::Tag      => " Add tags ",
+            Prompt::Priority => " Set priority (high, medium, low, none) ",
+            Prompt::Move     => " Move to list ",
+            Prompt::Switch   => " Switch to list ",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default)]
+enum Focus {
+    #[default]
+    Input,
+    Buttons,
+    List,
+}
+
+impl Focus {
+    fn context(&self) -> Context {
+        match self {
+            Focus::Input   => Context::Input,
+            Focus::Buttons => Context::Buttons,
+            Focus::List    => Context::List,
+        }
+    }
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            exit: false,
+            todolist: Vec::new(),
+            focus: Focus::Input,
+            inputter: Inputter::new(),
+            first_todo: true,
+            editing: None,
+            areas: Areas::default(),
+            last_click: None,
+            dragging: false,
+            button: 0,
+            hover: None,
+            pressed: None,
+            history: History::default(),
+            clipboard: Clipboard::default(),
+            input_history: InputHistory::default(),
+            pending_paste: None,
+            vim: Vim::default(),
+            insert_at: None,
+            completion: Completion::default(),
+            search: None,
+            sort: Sort::default(),
+            sort_modes: SortModes::default(),
+            collator: collator(""),
+            sort_menu: None,
+            palette: None,
+            help: None,
+            keymap: Keymap::default(),
+            pending_keys: Vec::new(),
+            list: DEFAULT_LIST.to_string(),
+            lists: Vec::new(),
+            prompt: None,
+            theme: Theme::default(),
+            themes: Theme::builtin(),
+            board: false,
+            grouping: Grouping::default(),
+        }
+    }
+
+    /// Get the border style based on current widget's required focus.
     fn get_border(&self, check_focus: &Focus) -> Style {
         if self.focus == *check_focus {
-            if self.focus == Focus::Input && self.editing.is_some() {
-                return Style::new().yellow();
+            if self.focus == Focus::Input && (self.editing.is_some() || self.prompt.is_some()) {
+                return self.theme.editing;
+            }
+            return self.theme.focus;
+        }
+        self.theme.border_style
+    }
+
+    /// Border of a widget, standing out when it has focus.
+    fn get_border_set(&self, check_focus: &Focus) -> border::Set {
+        if self.focus == *check_focus { self.theme.focus_border } else { self.theme.border }
+    }
+
+    /// Style of a button, based on whether it is pressed, focused, or hovered.
+    fn button_style(&self, i: usize) -> Style {
+        if self.pressed == Some(i) {
+            self.theme.pressed
+        } else if self.focus == Focus::Buttons && self.button == i {
+            self.theme.button
+        } else if self.hover == Some(i) {
+            self.theme.hover
+        } else {
+            self.theme.button_idle
+        }
+    }
+
+    /// Perform the action of a button.
+    fn press_button(&mut self, i: usize, state: &mut ListState) {
+        if self.todolist.is_empty() {
+            return;
+        }
+        match i {
+            0 => self.complete_all(state),
+            _ => self.clear_completed(state),
+        }
+    }
+
+    fn snapshot(&self, state: &ListState) -> Snapshot {
+        Snapshot {
+            todolist: self.todolist.clone(),
+            selected: state.selected(),
+            list: self.list.clone(),
+            lists: self.lists.clone(),
+        }
+    }
+
+    /// Record the current state in the undo history, before a mutation.
+    fn checkpoint(&mut self, state: &ListState) {
+        self.history.push(self.snapshot(state));
+    }
+
+    fn undo(&mut self, state: &mut ListState) {
+        if let Some(prev) = self.history.undo(self.snapshot(state)) {
+            self.restore_snapshot(prev, state);
+        }
+    }
+
+    fn redo(&mut self, state: &mut ListState) {
+        if let Some(next) = self.history.redo(self.snapshot(state)) {
+            self.restore_snapshot(next, state);
+        }
+    }
+
+    fn restore_snapshot(&mut self, snapshot: Snapshot, state: &mut ListState) {
+        // The item being edited may no longer exist.
+        if self.editing.is_some() || self.prompt.is_some() {
+            self.cancel_edit();
+        }
+        self.todolist = snapshot.todolist;
+        self.lists = snapshot.lists;
+        if self.list != snapshot.list {
+            self.sort = self.sort_modes.get(&snapshot.list);
+            self.list = snapshot.list;
+        }
+        state.select(snapshot.selected);
+    }
+
+    fn complete_all(&mut self, state: &ListState) {
+        if self.todolist.is_empty() {
+            return;
+        }
+        let items: Vec<usize> = (0..self.todolist.len()).collect();
+        self.complete_items(&items, state);
+    }
+
+    /// Complete the given todos that are still active.
+    fn complete_items(&mut self, items: &[usize], state: &ListState) {
+        self.checkpoint(state);
+        let now = now();
+        for &i in items {
+            if !self.todolist[i].complete {
+                self.todolist[i].complete(now);
+            }
+        }
+    }
+
+    /// Clear completed items and update selection index.
+    fn clear_completed(&mut self, state: &mut ListState) {
+        let items: Vec<usize> = (0..self.todolist.len()).filter(|&i| self.todolist[i].complete).collect();
+        if !items.is_empty() {
+            self.checkpoint(state);
+            self.remove_items(&items, state);
+        }
+    }
+
+    /// Remove the given todos, returning them. The selection is kept if it
+    /// remains, or else moves to the item shown in its place.
+    fn remove_items(&mut self, items: &[usize], state: &mut ListState) -> Todos {
+        let pos = self.position(state);
+        let sel = state.selected().filter(|s| !items.contains(s));
+        let mut removed = Vec::new();
+        let mut kept = Vec::new();
+        for (i, t) in std::mem::take(&mut self.todolist).into_iter().enumerate() {
+            if items.contains(&i) { removed.push(t) } else { kept.push(t) }
+        }
+        self.todolist = kept;
+        state.select(sel.map(|s| s - items.iter().filter(|&&i| i < s).count()));
+        if sel.is_none() {
+            if let Some(pos) = pos {
+                self.select_index(pos, state);
+            }
+        }
+        removed
+    }
+
+    /// Todos marked for a bulk operation, or else the selection.
+    fn targets(&self, state: &ListState) -> Vec<usize> {
+        let marked: Vec<usize> = (0..self.todolist.len()).filter(|&i| self.todolist[i].marked).collect();
+        if marked.is_empty() { state.selected().into_iter().collect() } else { marked }
+    }
+
+    fn clear_marks(&mut self) {
+        for t in &mut self.todolist {
+            t.marked = false;
+        }
+    }
+
+    /// Mark the selection, move it, and mark the new selection too.
+    fn extend_marks(&mut self, offset: isize, state: &mut ListState) {
+        if let Some(sel) = state.selected() {
+            self.todolist[sel].marked = true;
+            self.select_offset(offset, state);
+        }
+        if let Some(sel) = state.selected() {
+            self.todolist[sel].marked = true;
+        }
+    }
+
+    /// Toggle completion of the marked todos or the selection. Several are
+    /// all completed, unless they already are.
+    fn toggle_selection(&mut self, state: &mut ListState) {
+        let items = self.targets(state);
+        if items.iter().all(|&i| self.todolist[i].complete) {
+            if !items.is_empty() {
+                self.checkpoint(state);
+            }
+            for i in items {
+                self.todolist[i].toggle();
+            }
+        } else {
+            self.complete_items(&items, state);
+        }
+    }
+
+    /// Copy the name of the current selection, if any, to the clipboard.
+    fn copy_selection(&mut self, state: &ListState) {
+        if let Some(sel) = state.selected() {
+            self.clipboard.copy(&self.todolist[sel].name);
+        }
+    }
+
+    /// Copy the list to the clipboard as markdown checkboxes, which can be
+    /// pasted back in.
+    fn export(&mut self) {
+        let now = now();
+        let lines: Vec<String> = self.order().into_iter().map(|i| {
+            let t = &self.todolist[i];
+            format!("- [{}] {}", if t.complete { "x" } else { " " }, t.quick_add(now))
+        }).collect();
+        if !lines.is_empty() {
+            self.clipboard.copy(&lines.join("\n"));
+        }
+    }
+
+    /// Switch to the theme after the current one.
+    fn next_theme(&mut self) {
+        let i = self.themes.iter().position(|t| t.name == self.theme.name).map_or(0, |i| i + 1);
+        if let Some(theme) = self.themes.get(i % self.themes.len().max(1)) {
+            self.theme = theme.clone();
+        }
+    }
+
+    /// Do an action from its key or the palette.
+    fn perform(&mut self, action: Action, state: &mut ListState) {
+        match action {
+            Action::Add            => self.focus = Focus::Input,
+            Action::Edit           => self.begin_editing(state),
+            Action::Toggle         => self.toggle_selection(state),
+            Action::Delete         => self.delete_selection(state),
+            Action::MarkAll        => self.complete_all(state),
+            Action::ClearCompleted => self.clear_completed(state),
+            Action::Mark => {
+                if let Some(sel) = state.selected() {
+                    self.todolist[sel].marked ^= true;
+                }
+            }
+            Action::Tag            => self.begin_prompt(Prompt::Tag, state),
+            Action::SetPriority    => self.begin_prompt(Prompt::Priority, state),
+            Action::MoveToList     => self.begin_prompt(Prompt::Move, state),
+            Action::SwitchList     => self.begin_prompt(Prompt::Switch, state),
+            Action::Sort           => self.open_sort_menu(),
+            Action::Search => {
+                self.focus = Focus::List;
+                self.begin_search(state);
+            }
+            Action::NextMatch      => self.select_match(true, state),
+            Action::PreviousMatch  => self.select_match(false, state),
+            Action::ClearMarks => {
+                self.search = None;
+                self.clear_marks();
+            }
+            Action::Copy           => self.copy_selection(state),
+            Action::Export         => self.export(),
+            Action::Theme          => self.next_theme(),
+            Action::Board          => self.board = !self.board,
+            Action::GroupBoard => {
+                self.board = true;
+                self.grouping = match self.grouping {
+                    Grouping::Status => Grouping::Tags,
+                    Grouping::Tags   => Grouping::Status,
+                };
+            }
+            // Cards only move where their columns can be seen.
+            Action::MoveCard if self.board => self.move_cards(state),
+            Action::Down | Action::Up | Action::PageDown | Action::PageUp | Action::HalfPageDown
+            | Action::HalfPageUp | Action::First | Action::Last | Action::ColumnLeft
+            | Action::ColumnRight if self.board => self.select_card(action, state),
+            Action::ColumnLeft | Action::ColumnRight | Action::MoveCard => {}
+            Action::Undo           => self.undo(state),
+            Action::Redo           => self.redo(state),
+            Action::Help           => self.help = Some(0),
+            Action::Quit           => self.exit = true,
+            Action::Palette        => self.palette = Some(Palette::default()),
+            Action::FocusNext | Action::FocusPrevious => {
+                self.insert_at = None;
+                self.vim.reset();
+                let next = action == Action::FocusNext;
+                self.focus = match self.focus {
+                    Focus::Input   => if next { Focus::Buttons } else { Focus::List },
+                    Focus::Buttons => if next { Focus::List } else { Focus::Input },
+                    Focus::List    => if next { Focus::Input } else { Focus::Buttons },
+                };
+            }
+            Action::Down           => self.select_offset(1, state),
+            Action::Up             => self.select_offset(-1, state),
+            Action::PageDown       => self.select_offset(self.page_size(), state),
+            Action::PageUp         => self.select_offset(-self.page_size(), state),
+            Action::HalfPageDown   => self.select_offset((self.page_size() + 1) / 2, state),
+            Action::HalfPageUp     => self.select_offset(-(self.page_size() + 1) / 2, state),
+            Action::First          => self.select_index(0, state),
+            Action::Last           => self.select_index(usize::MAX, state),
+            Action::ExtendDown     => self.extend_marks(1, state),
+            Action::ExtendUp       => self.extend_marks(-1, state),
+            Action::PreviousButton => self.button = self.button.saturating_sub(1),
+            Action::NextButton     => self.button = (self.button + 1).min(BUTTONS.len() - 1),
+            Action::PressButton    => self.press_button(self.button, state),
+            Action::Save => {
+                let name = self.inputter.input.clone();
+                if let Some(prompt) = self.prompt {
+                    self.finish_prompt(prompt, &name, state);
+                } else if !name.is_empty() {
+                    if let Some(idx) = self.editing {
+                        self.finish_editing(name, idx, state);
+                    } else {
+                        self.new_item(name, state);
+                    }
+                }
+            }
+            Action::Cancel => {
+                // Treat as tab if not currently editing.
+                self.focus = Focus::List;
+                self.insert_at = None;
+                if self.editing.is_some() || self.prompt.is_some() {
+                    self.cancel_edit();
+                }
+            }
+            _ => self.perform_input(action),
+        }
+    }
+
+    /// Do an action that only edits the input.
+    fn perform_input(&mut self, action: Action) {
+        let inp = &mut self.inputter;
+        match action {
+            Action::LineStart       => inp.cursor_to_start(),
+            Action::LineEnd         => inp.cursor_to_end(),
+            Action::CharLeft        => inp.left(),
+            Action::CharRight       => inp.right(),
+            Action::WordLeft        => inp.word_left(),
+            Action::WordRight       => inp.word_right(),
+            Action::DeleteLeft      => inp.delete_left(),
+            Action::DeleteRight     => inp.delete_right(),
+            Action::KillUnixWord    => inp.kill_unix_word_left(),
+            Action::KillWordLeft    => inp.kill_word_left(),
+            Action::KillWordRight   => inp.kill_word_right(),
+            Action::KillToStart     => inp.kill_to_start(),
+            Action::KillToEnd       => inp.kill_to_end(),
+            Action::Yank            => inp.yank(),
+            Action::YankPop         => inp.yank_pop(),
+            Action::Transpose       => inp.transpose(),
+            Action::SelectLeft      => inp.drag_to(inp.cursor.saturating_sub(1)),
+            Action::SelectRight     => inp.drag_to(inp.cursor + 1),
+            Action::SelectToStart   => inp.drag_to(0),
+            Action::SelectToEnd     => inp.drag_to(inp.len()),
+            Action::SelectWordLeft  => inp.drag_to(inp.word_start(inp.cursor, is_word)),
+            Action::SelectWordRight => inp.drag_to(inp.word_end(inp.cursor)),
+            Action::CopyText | Action::CutText => {
+                if let Some(text) = inp.selected_text() {
+                    self.clipboard.copy(text);
+                }
+                if action == Action::CutText {
+                    self.inputter.delete_selection();
+                }
+            }
+            // Taken as a paste, so that lines copied together become todos.
+            Action::PasteText       => self.handle_paste(&self.clipboard.register.clone()),
+            Action::HistoryPrevious => {
+                let text = self.input_history.prev(&self.inputter.input).map(str::to_string);
+                self.recall(text);
+            }
+            Action::HistoryNext => {
+                let text = self.input_history.next().map(str::to_string);
+                self.recall(text);
+            }
+            Action::HistorySearch   => self.search_history(),
+            _ => {}
+        }
+    }
+
+    fn handle_help_key(&mut self, key: KeyEvent) {
+        let Some(scroll) = self.help else {
+            return;
+        };
+        let page = u16::try_from(self.areas.list_page() * 3).unwrap_or(1);
+        self.help = match key.code {
+            KeyCode::Down | KeyCode::Char('j') => Some(scroll.saturating_add(1)),
+            KeyCode::Up   | KeyCode::Char('k') => Some(scroll.saturating_sub(1)),
+            KeyCode::PageDown => Some(scroll.saturating_add(page)),
+            KeyCode::PageUp   => Some(scroll.saturating_sub(page)),
+            KeyCode::Esc | KeyCode::Char('?' | 'q') => None,
+            _ => Some(scroll),
+        };
+    }
+
+    fn handle_palette_key(&mut self, key: KeyEvent, state: &mut ListState) {
+        let Some(palette) = &mut self.palette else {
+            return;
+        };
+        let count = palette.matches().len();
+        let control = key.modifiers == KeyModifiers::CONTROL;
+        match key.code {
+            KeyCode::Esc => self.palette = None,
+            KeyCode::Enter => {
+                let action = palette.matches().get(palette.selected).map(|(a, _)| *a);
+                self.palette = None;
+                if let Some(action) = action {
+                    self.perform(action, state);
+                }
+            }
+            KeyCode::Down => palette.selected = (palette.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Up   => palette.selected = palette.selected.saturating_sub(1),
+            KeyCode::Char('n') if control => palette.selected = (palette.selected + 1).min(count.saturating_sub(1)),
+            KeyCode::Char('p') if control => palette.selected = palette.selected.saturating_sub(1),
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.selected = 0;
+            }
+            KeyCode::Char(c) if !control => {
+                palette.query.push(c);
+                palette.selected = 0;
+            }
+            _ => {}
+        }
+    }
+
+    /// Delete current selection, if any, and select the previous item.
+    fn delete_selection(&mut self, state: &mut ListState) {
+        let items = self.targets(state);
+        if self.todolist.iter().any(|t| t.marked) {
+            self.checkpoint(state);
+            self.remove_items(&items, state);
+        } else if let Some(pos) = self.position(state) {
+            self.checkpoint(state);
+            self.remove_items(&items, state);
+            self.select_index(pos.saturating_sub(1), state);
+        }
+    }
+
+    /// Borrow the input for a prompt, if there is anything to act on.
+    fn begin_prompt(&mut self, prompt: Prompt, state: &ListState) {
+        if prompt != Prompt::Switch && self.targets(state).is_empty() {
+            return;
+        }
+        self.vim.mode = Mode::Insert;
+        self.inputter.save();
+        self.inputter.reset();
+        self.focus = Focus::Input;
+        self.prompt = Some(prompt);
+    }
+
+    /// Apply a prompt to the marked todos or the selection, and restore
+    /// input. Invalid answers leave the prompt open.
+    fn finish_prompt(&mut self, prompt: Prompt, input: &str, state: &mut ListState) {
+        let input = input.trim();
+        let items = self.targets(state);
+        match prompt {
+            Prompt::Tag => {
+                let tags: Vec<&str> = input.split_whitespace().map(|t| t.trim_start_matches('#')).filter(|t| !t.is_empty()).collect();
+                if tags.is_empty() {
+                    return;
+                }
+                self.checkpoint(state);
+                for i in items {
+                    let todo = &mut self.todolist[i];
+                    for tag in &tags {
+                        if !todo.tags.iter().any(|t| t == tag) {
+                            todo.tags.push((*tag).to_string());
+                        }
+                    }
+                }
+            }
+            Prompt::Priority => {
+                let priority = parse_priority(input.trim_start_matches('!'));
+                if priority.is_none() && !matches!(input, "none" | "n" | "0" | "") {
+                    return;
+                }
+                self.checkpoint(state);
+                for i in items {
+                    self.todolist[i].priority = priority;
+                }
+            }
+            Prompt::Move => {
+                if input.is_empty() {
+                    return;
+                }
+                if input != self.list {
+                    self.checkpoint(state);
+                    let mut moved = self.remove_items(&items, state);
+                    for t in &mut moved {
+                        t.marked = false;
+                    }
+                    match self.lists.iter_mut().find(|(name, _)| name == input) {
+                        Some((_, todos)) => todos.append(&mut moved),
+                        None => self.lists.push((input.to_string(), moved)),
+                    }
+                }
+            }
+            Prompt::Switch => {
+                if input.is_empty() {
+                    return;
+                }
+                self.switch_list(input, state);
+            }
+        }
+        self.clear_marks();
+        self.prompt = None;
+        self.focus = Focus::List;
+        self.inputter.restore();
+    }
+
+    /// Show another list, making it if there is none by that name.
+    fn switch_list(&mut self, name: &str, state: &mut ListState) {
+        if name == self.list {
+            return;
+        }
+        let todos = match self.lists.iter().position(|(n, _)| n == name) {
+            Some(i) => self.lists.remove(i).1,
+            None => Vec::new(),
+        };
+        let previous = std::mem::replace(&mut self.todolist, todos);
+        self.lists.push((std::mem::replace(&mut self.list, name.to_string()), previous));
+        self.sort = self.sort_modes.get(name);
+        self.search = None;
+        state.select(None);
+        *state.offset_mut() = 0;
+        self.select_index(0, state);
+    }
+
+    /// Indices of todos in the order they are shown.
+    fn order(&self) -> Vec<usize> {
+        self.sort.order(&self.todolist, &self.collator)
+    }
+
+    /// Position of the selection in the order shown.
+    fn position(&self, state: &ListState) -> Option<usize> {
+        let sel = state.selected()?;
+        self.order().iter().position(|&i| i == sel)
+    }
+
+    /// Update selection by a given offset clamped to 0 and max list index.
+    fn select_offset(&mut self, offset: isize, state: &mut ListState) {
+        if let Some(pos) = self.position(state) {
+            self.select_index(pos.saturating_add_signed(offset), state);
+        }
+    }
+
+    /// Scroll the list by a number of items, as far as the last page. The
+    /// selection only moves when it would leave the view.
+    fn scroll_list(&mut self, by: isize, state: &mut ListState) {
+        let page = self.areas.list_page();
+        let offset = state.offset().saturating_add_signed(by).min(self.order().len().saturating_sub(page));
+        *state.offset_mut() = offset;
+        if let Some(pos) = self.position(state) {
+            self.select_index(pos.clamp(offset, offset + page - 1), state);
+        }
+    }
+
+    /// Context of the focused widget, with the board in place of the list.
+    fn context(&self) -> Context {
+        match self.focus {
+            Focus::List if self.board => Context::Board,
+            _ => self.focus.context(),
+        }
+    }
+
+    /// Columns of the board, as titles and the todos in them in the order
+    /// shown.
+    fn columns(&self) -> Vec<(String, Vec<usize>)> {
+        let order = self.order();
+        match self.grouping {
+            Grouping::Status => {
+                let column = |t: &Todo| if t.complete { 2 } else { usize::from(t.started) };
+                ["Todo", "In progress", "Done"].iter().enumerate().map(|(c, title)| {
+                    let items = order.iter().copied().filter(|&i| column(&self.todolist[i]) == c).collect();
+                    (title.to_string(), items)
+                }).collect()
+            }
+            Grouping::Tags => {
+                let mut columns: Vec<(String, Vec<usize>)> = Vec::new();
+                let mut untagged = Vec::new();
+                for i in order {
+                    let Some(tag) = self.todolist[i].tags.first() else {
+                        untagged.push(i);
+                        continue;
+                    };
+                    let title = format!("#{tag}");
+                    match columns.iter_mut().find(|(t, _)| *t == title) {
+                        Some((_, items)) => items.push(i),
+                        None => columns.push((title, vec![i])),
+                    }
+                }
+                columns.sort_by(|(a, _), (b, _)| self.collator.compare(a, b));
+                columns.push(("Untagged".to_string(), untagged));
+                columns
+            }
+        }
+    }
+
+    /// Select a card by an action moving within its column or to the next
+    /// column with cards either side, or the first card if none is selected.
+    fn select_card(&mut self, action: Action, state: &mut ListState) {
+        let columns = self.columns();
+        let Some((c, row)) = card_position(&columns, state) else {
+            state.select(columns.iter().find_map(|(_, items)| items.first().copied()));
+            return;
+        };
+        let page = usize::from(self.areas.list_inner().height).max(1);
+        let filled = |c: &usize| !columns[*c].1.is_empty();
+        let (c, row) = match action {
+            Action::Down         => (c, row + 1),
+            Action::Up           => (c, row.saturating_sub(1)),
+            Action::PageDown     => (c, row + page),
+            Action::PageUp       => (c, row.saturating_sub(page)),
+            Action::HalfPageDown => (c, row + page.div_ceil(2)),
+            Action::HalfPageUp   => (c, row.saturating_sub(page.div_ceil(2))),
+            Action::First        => (c, 0),
+            Action::Last         => (c, usize::MAX),
+            Action::ColumnLeft   => ((0..c).rev().find(filled).unwrap_or(c), row),
+            Action::ColumnRight  => ((c + 1..columns.len()).find(filled).unwrap_or(c), row),
+            _ => return,
+        };
+        let items = &columns[c].1;
+        state.select(Some(items[row.min(items.len() - 1)]));
+    }
+
+    /// Move the marked cards, or the selected one, to the next column. Done
+    /// todos go back to the start, as do untagged ones when grouped by tag.
+    fn move_cards(&mut self, state: &mut ListState) {
+        let items = self.targets(state);
+        let columns = self.columns();
+        if items.is_empty() || (self.grouping == Grouping::Tags && columns.len() == 1) {
+            return;
+        }
+        self.checkpoint(state);
+        let now = now();
+        for i in items {
+            let todo = &mut self.todolist[i];
+            match self.grouping {
+                Grouping::Status if todo.complete => todo.toggle(),
+                Grouping::Status if todo.started  => todo.complete(now),
+                Grouping::Status                  => todo.started = true,
+                // Cards trade the tag of their column for the next column's,
+                // keeping their other tags. Untagged is left out, going back
+                // round to the first tag instead, so no tag is ever lost.
+                Grouping::Tags => {
+                    let tags: Vec<&str> = columns.iter().filter_map(|(title, _)| title.strip_prefix('#')).collect();
+                    let current = todo.tags.first().cloned();
+                    let next = match current.as_deref().and_then(|tag| tags.iter().position(|&t| t == tag)) {
+                        Some(c) => tags[(c + 1) % tags.len()],
+                        None => tags[0],
+                    };
+                    todo.tags.retain(|t| Some(t) != current.as_ref() && t != next);
+                    todo.tags.insert(0, next.to_string());
+                }
+            }
+        }
+    }
+
+    /// Select the item shown at a position clamped to the end of the list.
+    fn select_index(&mut self, pos: usize, state: &mut ListState) {
+        let order = self.order();
+        if let Some(max) = order.len().checked_sub(1) {
+            state.select(Some(order[pos.min(max)]));
+        }
+    }
+
+    /// Open the sort menu at the current mode.
+    fn open_sort_menu(&mut self) {
+        self.sort_menu = SORTS.iter().position(|&s| s == self.sort);
+    }
+
+    fn set_sort(&mut self, sort: Sort) {
+        self.sort = sort;
+        self.sort_modes.set(&self.list, sort);
+    }
+
+    fn handle_sort_menu_key(&mut self, key: KeyEvent) {
+        let Some(i) = self.sort_menu else {
+            return;
+        };
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => self.sort_menu = Some((i + 1).min(SORTS.len() - 1)),
+            KeyCode::Up   | KeyCode::Char('k') => self.sort_menu = Some(i.saturating_sub(1)),
+            KeyCode::Enter | KeyCode::Char(' ') => {
+                self.set_sort(SORTS[i]);
+                self.sort_menu = None;
+            }
+            KeyCode::Char(c @ '1'..='6') => {
+                self.set_sort(SORTS[usize::from(c as u8 - b'1')]);
+                self.sort_menu = None;
+            }
+            KeyCode::Esc | KeyCode::Char('s' | 'q') => self.sort_menu = None,
+            _ => {}
+        }
+    }
+
+    /// Number of items that fit in the list, as an offset for `select_offset`.
+    fn page_size(&self) -> isize {
+        isize::try_from(self.areas.list_page()).unwrap_or(1)
+    }
+
+    /// Discard current input and stop editing.
+    fn cancel_edit(&mut self) {
+        self.inputter.restore();
+        self.editing = None;
+        self.prompt = None;
+    }
+
+    /// Save current input and take over input for editing.
+    fn begin_editing(&mut self, state: &ListState) {
+        if let Some(sel) = state.selected() {
+            self.vim.mode = Mode::Insert;
+            self.inputter.save();
+            self.focus = Focus::Input;
+            self.editing = Some(sel);
+            self.inputter.input = self.todolist[sel].quick_add(now());
+            self.inputter.cursor_to_end();
+        }
+    }
+
+    /// Add a todo from quick-add syntax, unless it leaves an empty name.
+    fn new_item(&mut self, input: String, state: &mut ListState) {
+        let todo = Todo::from_quick_add(&input, now());
+        if todo.name.is_empty() {
+            return;
+        }
+        self.checkpoint(state);
+        self.input_history.add(&input);
+        let idx = self.insert_at.map_or(self.todolist.len(), |i| i.min(self.todolist.len()));
+        self.todolist.insert(idx, todo);
+        state.select(Some(idx));
+        // Keep adding after the new one, like opening lines in vim.
+        if self.insert_at.is_some() {
+            self.insert_at = Some(idx + 1);
+        }
+        self.first_todo = false;
+        self.inputter.reset();
+    }
+
+    /// Save edits and restore input.
+    fn finish_editing(&mut self, input: String, idx: usize, state: &ListState) {
+        let todo = Todo {
+            complete: self.todolist[idx].complete,
+            completed_at: self.todolist[idx].completed_at,
+            marked: self.todolist[idx].marked,
+            started: self.todolist[idx].started,
+            ..Todo::from_quick_add(&input, now())
+        };
+        if todo.name.is_empty() {
+            return;
+        }
+        if self.todolist[idx] != todo {
+            self.checkpoint(state);
+            self.todolist[idx] = todo;
+        }
+        self.focus = Focus::List;
+        self.editing = None;
+        self.inputter.restore();
+    }
+
+    /// Draw to frame using pre-initialized `ListState`.
+    fn draw(&mut self, frame: &mut Frame, liststate: &mut ListState) {
+        let areas = Areas::new(frame.size());
+        self.inputter.scroll_to_cursor(areas.input_text_width());
+
+        let theme = &self.theme;
+        let header = Paragraph::new(theme.header.as_str()).alignment(Alignment::Center);
+        frame.render_widget(&header, areas.header);
+
+        let now = now();
+        // Prompts are not quick-add syntax.
+        let add = parse_quick_add(if self.prompt.is_none() { &self.inputter.input } else { "" }, now);
+        let input_text = if self.inputter.render_placeholder && self.editing.is_none() && self.prompt.is_none() {
+            Line::from(Span::styled("What needs to be done?", theme.dim))
+        } else {
+            let styles = add.tokens.iter()
+                .map(|(range, token)| (range.clone(), token.style(theme)))
+                .collect::<Vec<_>>();
+            let clip = Span::styled(theme.ellipsis.clone(), theme.dim);
+            let mut line = self.inputter.view(areas.input_text_width(), &styles, theme.selection, &clip);
+            if let Some(rest) = self.suggestion().filter(|_| self.focus == Focus::Input) {
+                // The cursor is at the end, so the whole end of the input is in view.
+                let room = (areas.input_text_width() + self.inputter.scroll).saturating_sub(self.inputter.input.width());
+                let mut ghost = String::new();
+                for g in rest.graphemes(true) {
+                    if ghost.width() + g.width() >= room {
+                        break;
+                    }
+                    ghost.push_str(g);
+                }
+                line.spans.push(Span::styled(ghost, theme.dim));
+            }
+            line
+        };
+        let mut input_block = Block::bordered()
+            .border_set(self.get_border_set(&Focus::Input))
+            .padding(Padding::horizontal(1))
+            .border_style(self.get_border(&Focus::Input));
+        // Preview of the due date before saving.
+        if let Some(due) = add.due {
+            let preview = format!(" due {} ", due.fmt(now.date()));
+            input_block = input_block.title_bottom(Line::styled(preview, theme.due).right_aligned());
+        } else if add.tokens.iter().any(|(r, t)| *t == Token::Invalid && self.inputter.input[r.clone()].starts_with("due:")) {
+            input_block = input_block.title_bottom(Line::styled(" unknown date ", theme.overdue).right_aligned());
+        }
+        if let Some(prompt) = self.prompt {
+            input_block = input_block.title(prompt.title());
+            if matches!(prompt, Prompt::Move | Prompt::Switch) && !self.lists.is_empty() {
+                let names: Vec<&str> = self.lists.iter().map(|(name, _)| name.as_str()).collect();
+                input_block = input_block.title_bottom(Line::styled(format!(" {} ", names.join(", ")), theme.dim).right_aligned());
+            }
+        }
+        if let Some((query, found)) = &self.input_history.search {
+            let failing = if found.is_none() && !query.is_empty() { "failing " } else { "" };
+            input_block = input_block.title(format!(" {failing}reverse-i-search: {query} "));
+        }
+        let input_widget = Paragraph::new(input_text).block(input_block);
+        frame.render_widget(input_widget, areas.input);
+        if self.focus == Focus::Input {
+            let cursor_width = self.inputter.width_to(self.inputter.cursor) - self.inputter.scroll;
+            let input_cursor_absolute = areas.input.x + 2 + u16::try_from(cursor_width).unwrap();
+            frame.set_cursor(input_cursor_absolute, areas.input.y + 1);
+        }
+
+        for (i, (key, label)) in BUTTONS.iter().enumerate() {
+            let button = Line::from(vec![Span::styled(*key, theme.key), " ".into(), (*label).into()]);
+            frame.render_widget(
+                Paragraph::new(button).alignment(Alignment::Center).style(self.button_style(i)),
+                areas.buttons[i]
+            );
+        }
+
+        if self.focus == Focus::Input {
+            let editing_hint = vec![
+                Span::styled("enter", theme.key), ": save, ".into(),
+                Span::styled("esc", theme.key), ": cancel ".into()
+            ];
+            let input_hint = vec![Span::styled("enter", theme.key), ": save ".into()];
+            let hint = Line::from(if self.editing.is_some() || self.prompt.is_some() { editing_hint } else { input_hint });
+            // Only shown in the space left over by the buttons.
+            let buttons_end = areas.buttons[BUTTONS.len() - 1].right();
+            let free = areas.actions.right().saturating_sub(buttons_end);
+            if usize::from(free) > hint.width() {
+                frame.render_widget(Paragraph::new(hint).alignment(Alignment::Right), areas.actions);
+            }
+        }
+
+        if self.board {
+            self.draw_board(frame, areas.list, liststate, now);
+        } else {
+            self.draw_list(frame, &areas, liststate, now);
+        }
+
+        let itemsleft = Paragraph::new(
+            if self.first_todo {
+                String::new()
+            } else {
+                fmt_itemsleft(&self.todolist)
+            }
+        ).alignment(Alignment::Right);
+        frame.render_widget(&itemsleft, areas.itemsleft);
+        if self.vim.enabled {
+            frame.render_widget(Paragraph::new(Span::styled(self.vim.status(&self.focus), theme.key)), areas.itemsleft);
+        }
+
+        let bindings = self.keymap.footer(self.context(), usize::from(areas.footer.width), theme.key);
+        frame.render_widget(Paragraph::new(bindings).alignment(Alignment::Center), areas.footer);
+
+        if self.focus == Focus::Input && !self.completion.items.is_empty() {
+            self.draw_completion(frame, &areas);
+        }
+
+        if let Some(todos) = &self.pending_paste {
+            Self::draw_paste_preview(frame, areas.list, todos, theme);
+        }
+
+        if let Some(selected) = self.sort_menu {
+            self.draw_sort_menu(frame, areas.list, selected);
+        }
+
+        if let Some(palette) = &self.palette {
+            Self::draw_palette(frame, palette, &self.keymap, theme);
+        }
+
+        if self.help.is_some() {
+            self.draw_help(frame);
+        }
+    }
+
+    /// Draw the todos as a list. Items are shown in sort order, so the list
+    /// is drawn with its own state holding the position of the selection.
+    /// While typing a search, only matches are listed.
+    fn draw_list(&self, frame: &mut Frame, areas: &Areas, liststate: &mut ListState, now: NaiveDateTime) {
+        let theme = &self.theme;
+        let found = self.search.as_ref().map(|s| self.search_matches(&s.query)).unwrap_or_default();
+        let typing = self.search.as_ref().is_some_and(|s| s.typing);
+        let mut list_block = Block::bordered()
+            .border_set(self.get_border_set(&Focus::List))
+            .border_style(self.get_border(&Focus::List));
+        let (items, mut list_state): (Vec<Text>, ListState) = if typing {
+            let selected = found.iter().position(|(i, ..)| Some(*i) == liststate.selected());
+            let items = found.iter().map(|(i, _, m)| self.todolist[*i].fmt_item(now, m, theme));
+            (items.collect(), ListState::default().with_selected(selected))
+        } else {
+            let items = self.order().into_iter().map(|i| {
+                let matched = found.iter().find(|(j, ..)| *j == i).map_or(&[][..], |f| &f.2);
+                self.todolist[i].fmt_item(now, matched, theme)
+            });
+            let state = ListState::default()
+                .with_selected(self.position(liststate))
+                .with_offset(liststate.offset());
+            (items.collect(), state)
+        };
+        if !self.lists.is_empty() && self.search.is_none() {
+            list_block = list_block.title(format!(" {} ", self.list));
+        }
+        if self.sort != Sort::Insertion {
+            list_block = list_block.title_bottom(format!(" {} ", self.sort.name().to_lowercase()));
+        }
+        let marked = self.todolist.iter().filter(|t| t.marked).count();
+        if marked > 0 {
+            list_block = list_block.title_bottom(Line::from(format!(" {marked} marked ")).right_aligned());
+        }
+        if let Some(search) = &self.search {
+            list_block = list_block
+                .title(format!(" /{} ", search.query))
+                .title(Line::from(format!(" {}/{} ", found.len(), self.todolist.len())).right_aligned());
+            if search.typing {
+                let x = areas.list.x + 3 + u16::try_from(search.query.width()).unwrap_or(0);
+                frame.set_cursor(x.min(areas.list.right() - 1), areas.list.y);
+            }
+        }
+        let count = items.len();
+        let todolist = List::new(items)
+            .block(list_block)
+            .highlight_style(theme.highlight);
+
+        frame.render_stateful_widget(&todolist, areas.list, &mut list_state);
+        if !typing {
+            *liststate.offset_mut() = list_state.offset();
+        }
+
+        // Scrollbar on the right border, only when the items overflow.
+        let page = areas.list_page();
+        if count > page {
+            let mut scrollstate = ScrollbarState::new(count - page + 1)
+                .position(list_state.offset())
+                .viewport_content_length(page);
+            frame.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .symbols(theme.scrollbar.clone())
+                    .style(theme.scrollbar_style)
+                    .begin_symbol(None)
+                    .end_symbol(None),
+                areas.list.inner(&Margin::new(0, 1)),
+                &mut scrollstate,
+            );
+        }
+    }
+
+    /// Draw the todos as cards in columns, the selected one highlighted.
+    fn draw_board(&self, frame: &mut Frame, area: Rect, state: &ListState, now: NaiveDateTime) {
+        let theme = &self.theme;
+        let columns = self.columns();
+        let selected = card_position(&columns, state);
+        for (c, rect) in board_layout(area, columns.len(), selected.map(|(c, _)| c)) {
+            let (title, items) = &columns[c];
+            let row = selected.filter(|(sc, _)| *sc == c).map(|(_, row)| row);
+            let focused = row.is_some() && self.focus == Focus::List;
+            let block = Block::bordered()
+                .title(format!(" {title} "))
+                .title_bottom(Line::from(format!(" {} ", items.len())).right_aligned())
+                .border_set(if focused { theme.focus_border } else { theme.border })
+                .border_style(if focused { theme.focus } else { theme.border_style });
+            let cards = List::new(items.iter().map(|&i| self.todolist[i].fmt_card(now, theme)))
+                .block(block)
+                .highlight_style(theme.highlight);
+            frame.render_stateful_widget(cards, rect, &mut ListState::default().with_selected(row));
+        }
+    }
+
+    /// Overlay of every key binding, grouped by where it applies.
+    fn draw_help(&mut self, frame: &mut Frame) {
+        let size = frame.size();
+        let mut lines = Vec::new();
+        for context in CONTEXTS {
+            if !lines.is_empty() {
+                lines.push(Line::default());
             }
-            return Style::new().blue();
+            lines.push(Line::from(Span::styled(context.name(), self.theme.heading)));
+            let mut seen = Vec::new();
+            for (_, _, action) in self.keymap.bindings.iter().filter(|(c, ..)| *c == context) {
+                if !seen.contains(action) {
+                    seen.push(*action);
+                    let keys = self.keymap.keys(*action, context).join("/");
+                    lines.push(Line::from(vec![Span::styled(format!("{keys:>22}  "), self.theme.key), action.name().into()]));
+                }
+            }
+            // TodoMVC has space toggle completion, so marking has its own key.
+            let marks = self.keymap.keys(Action::Mark, Context::List);
+            if context == Context::List && self.keymap.keys(Action::Toggle, Context::List).contains(&"space".to_string()) && !marks.is_empty() {
+                let note = format!("Space toggles completion as in TodoMVC, so {} marks todos.", marks.join("/"));
+                lines.push(Line::styled(note, self.theme.dim));
+            }
+        }
+        if self.vim.enabled {
+            lines.push(Line::default());
+            lines.push(Line::styled("Vim keys work in the list and input too, unless the config binds them.", self.theme.dim));
         }
-        Style::new()
+
+        let width = 72.min(size.width);
+        let height = size.height.saturating_sub(2).max(size.height.min(3));
+        let area = Rect::new(size.x + (size.width - width) / 2, size.y + (size.height - height) / 2, width, height);
+        let block = Block::bordered()
+            .title(" Key bindings ")
+            .title_bottom(Line::from(vec![Span::styled(" esc", self.theme.key), ": close ".into()]).right_aligned())
+            .border_set(self.theme.focus_border)
+            .border_style(self.theme.focus);
+        let room = block.inner(area).height;
+        let max = u16::try_from(lines.len()).unwrap_or(u16::MAX).saturating_sub(room);
+        let scroll = self.help.unwrap_or(0).min(max);
+        self.help = Some(scroll);
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(block).scroll((scroll, 0)), area);
     }
 
-    fn complete_all(&mut self) {
-        for t in &mut self.todolist {
-            t.complete = true;
+    /// Palette near the top of the screen, with the query above the actions.
+    fn draw_palette(frame: &mut Frame, palette: &Palette, keymap: &Keymap, theme: &Theme) {
+        let size = frame.size();
+        let found = palette.matches();
+        let width = 44.min(size.width);
+        let height = u16::try_from(found.len().min(10) + 4).unwrap_or(u16::MAX).min(size.height);
+        let area = Rect::new(size.x + (size.width - width) / 2, size.y + 2.min(size.height - height), width, height);
+        let block = Block::bordered()
+            .title(" Commands ")
+            .border_set(theme.focus_border)
+            .border_style(theme.focus);
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let query = Rect { height: 1.min(inner.height), ..inner };
+        let list = Rect { y: inner.y + 2, height: inner.height.saturating_sub(2), ..inner };
+        frame.render_widget(Paragraph::new(format!(" > {}", palette.query)), query);
+        frame.set_cursor(query.x + 3 + u16::try_from(palette.query.width()).unwrap_or(0), query.y);
+
+        let room = usize::from(list.width);
+        let items = found.iter().map(|(action, matched)| {
+            let key = keymap.key(*action).unwrap_or_default();
+            let mut spans = vec![" ".into()];
+            spans.extend(highlight(action.name(), 0, matched, Style::default(), theme.matched));
+            let gap = room.saturating_sub(action.name().width() + key.width() + 2);
+            spans.push(" ".repeat(gap).into());
+            spans.push(Span::styled(key, theme.dim));
+            Line::from(spans)
+        });
+        let list_widget = List::new(items).highlight_style(theme.highlight);
+        let selected = (!found.is_empty()).then_some(palette.selected);
+        frame.render_stateful_widget(list_widget, list, &mut ListState::default().with_selected(selected));
+    }
+
+    /// Popup over the list to choose how it is sorted.
+    fn draw_sort_menu(&self, frame: &mut Frame, list: Rect, selected: usize) {
+        let items = SORTS.iter().enumerate().map(|(i, &sort)| {
+            let mark = if sort == self.sort { self.theme.bullet.clone() } else { " ".repeat(self.theme.bullet.width()) };
+            Line::from(vec![Span::styled(format!(" {} ", i + 1), self.theme.key), format!("{mark} {}", sort.name()).into()])
+        });
+        let width = 26.min(list.width);
+        let height = u16::try_from(SORTS.len() + 2).unwrap_or(u16::MAX).min(list.height);
+        let area = Rect::new(list.x + (list.width - width) / 2, list.y + (list.height - height) / 2, width, height);
+        let menu = List::new(items)
+            .block(Block::bordered().title(" Sort by ").border_set(self.theme.focus_border).border_style(self.theme.focus))
+            .highlight_style(self.theme.highlight);
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(menu, area, &mut ListState::default().with_selected(Some(selected)));
+    }
+
+    /// Popup of completions under the word being completed.
+    fn draw_completion(&self, frame: &mut Frame, areas: &Areas) {
+        let items = &self.completion.items;
+        let start = self.inputter.input[..self.completion.start].graphemes(true).count();
+        let col = self.inputter.width_to(start).saturating_sub(self.inputter.scroll);
+        let width = items.iter().map(|i| i.width()).max().unwrap_or(0) + 2;
+        let height = items.len().min(6) + 2;
+        let size = frame.size();
+        let width = u16::try_from(width).unwrap_or(u16::MAX).min(size.width);
+        let x = (areas.input.x + 1 + u16::try_from(col).unwrap_or(0)).min(size.right() - width);
+        let area = Rect::new(x, areas.input.bottom(), width, u16::try_from(height).unwrap_or(0))
+            .intersection(size);
+        let list = List::new(items.iter().map(String::as_str))
+            .block(Block::bordered().border_set(self.theme.focus_border).border_style(self.theme.focus))
+            .highlight_style(self.theme.highlight);
+        let mut state = ListState::default().with_selected(self.completion.selected);
+        frame.render_widget(Clear, area);
+        frame.render_stateful_widget(list, area, &mut state);
+    }
+
+    /// Popup over the list to confirm creating todos from a paste.
+    fn draw_paste_preview(frame: &mut Frame, list: Rect, todos: &Todos, theme: &Theme) {
+        let n = todos.len();
+        // Room left by the borders, the question, the hint and the blank lines.
+        let room = usize::from(list.height.saturating_sub(6));
+        let shown = if n > room { room.saturating_sub(1) } else { n };
+        let mut lines = vec![Line::from(format!("Create {n} todos from the paste?")), Line::default()];
+        for t in &todos[..shown] {
+            lines.push(Line::from(format!("{} {}", if t.complete { &theme.checked } else { &theme.unchecked }, t.name)));
         }
+        if shown < n {
+            lines.push(Line::styled(format!("...and {} more", n - shown), theme.dim));
+        }
+        lines.push(Line::default());
+        lines.push(Line::from(vec![
+            Span::styled("enter", theme.key), ": create, ".into(),
+            Span::styled("esc", theme.key), ": cancel".into(),
+        ]));
+
+        let height = u16::try_from(lines.len() + 2).unwrap_or(u16::MAX).min(list.height);
+        let area = Rect { y: list.y + (list.height - height) / 2, height, ..list };
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(
+                Block::bordered()
+                    .title(" Paste ")
+                    .border_set(theme.focus_border)
+                    .padding(Padding::horizontal(1))
+                    .border_style(theme.editing)
+            ),
+            area,
+        );
     }
 
-    /// Clear completed items and update selection index.
-    fn clear_completed(&mut self, state: &mut ListState) {
-        let mut new_list = Vec::new();
-        let sel = match state.selected() {
-            None => self.todolist.len() - 1,
-            Some(i) => i,
-        };
-        let mut new_sel = sel;
-        let mut sel_cleared = true;
+    /// Draw and handle events until told to exit, after the given keys.
+    fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>, keys: Vec<KeyEvent>) -> Result<()> {
+        terminal.show_cursor()?;
+        let mut keys = keys.into_iter();
+
+        let mut liststate = ListState::default();
 
-        for (i, t) in self.todolist.iter().enumerate() {
-            if t.complete {
-                if i < sel {
-                    // An item above selection is cleared, shift selection up.
-                    new_sel -= 1;
+        // Main loop
+        while !self.exit {
+            let completed = terminal.draw(|frame| self.draw(frame, &mut liststate))?;
+            self.areas = Areas::new(completed.area);
+            if let Some(osc52) = self.clipboard.pending.take() {
+                terminal.backend_mut().write_all(osc52.as_bytes())?;
+                terminal.backend_mut().flush()?;
+            }
+
+            if let Some(key) = keys.next() {
+                self.handle_key(key, &mut liststate);
+                continue;
+            }
+
+            // Blocks until there's an event. I think.
+            match event::read()? {
+                Event::Key(key_event) => self.handle_key(key_event, &mut liststate),
+                Event::Mouse(mouse_event) => self.handle_mouse(mouse_event, &mut liststate),
+                Event::Paste(text) => self.handle_paste(&text),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Index of the card drawn at the given cell of the board, if any.
+    fn card_at(&self, col: u16, row: u16, state: &ListState) -> Option<usize> {
+        let columns = self.columns();
+        let selected = card_position(&columns, state);
+        let (c, rect) = board_layout(self.areas.list, columns.len(), selected.map(|(c, _)| c))
+            .into_iter()
+            .find(|(_, rect)| hit(rect.inner(&Margin::new(1, 1)), col, row))?;
+        // Columns scroll only as far as keeps the selection in view.
+        let height = usize::from(rect.height.saturating_sub(2));
+        let offset = selected.filter(|(sc, _)| *sc == c).map_or(0, |(_, r)| (r + 1).saturating_sub(height));
+        columns[c].1.get(offset + usize::from(row - rect.y - 1)).copied()
+    }
+
+    /// Index of the todo item drawn at the given row of the list, if any.
+    fn item_at(&self, row: u16, state: &ListState) -> Option<usize> {
+        let inner = self.areas.list_inner();
+        let pos = state.offset() + usize::from(row - inner.y) / 3;
+        self.order().get(pos).copied()
+    }
+
+    /// Index of the button at the given cell, if any.
+    fn button_at(&self, col: u16, row: u16) -> Option<usize> {
+        self.areas.buttons.iter().position(|&area| hit(area, col, row))
+    }
+
+    /// Grapheme index in the input under the given column.
+    fn input_index_at(&self, column: u16) -> usize {
+        let offset = usize::from(column.saturating_sub(self.areas.input.x + 2));
+        self.inputter.index_at_width(self.inputter.scroll + offset)
+    }
+
+    /// Place the cursor on click, select a word on double-click.
+    fn click_input(&mut self, col: u16, row: u16) {
+        self.focus = Focus::Input;
+        let idx = self.input_index_at(col);
+        let double = self.last_click.is_some_and(|(time, c, r)| {
+            c == col && r == row && time.elapsed() < DOUBLE_CLICK
+        });
+        if double {
+            self.inputter.select_word(idx);
+            self.last_click = None;
+        } else {
+            self.inputter.select_from(idx);
+            self.last_click = Some((Instant::now(), col, row));
+            self.dragging = true;
+        }
+    }
+
+    fn handle_mouse(&mut self, mouse: MouseEvent, state: &mut ListState) {
+        // Rows don't line up with todos while the list is filtered, and
+        // overlays cover what would be clicked.
+        if self.search.as_ref().is_some_and(|s| s.typing) {
+            return;
+        }
+        if self.palette.is_some() || self.help.is_some() || self.sort_menu.is_some() || self.pending_paste.is_some() {
+            return;
+        }
+        let (col, row) = (mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                if hit(self.areas.input, col, row) {
+                    self.inputter.next_command();
+                    self.click_input(col, row);
+                    return;
                 }
-            } else {
-                new_list.push(Todo { name: t.name.clone(), complete: t.complete });
-                if i == sel {
-                    sel_cleared = false;
+                if let Some(i) = self.button_at(col, row) {
+                    self.pressed = Some(i);
+                    return;
+                }
+                if !hit(self.areas.list, col, row) {
+                    return;
+                }
+                if self.editing.is_some() || self.prompt.is_some() {
+                    self.cancel_edit();
+                }
+                self.focus = Focus::List;
+
+                if self.board {
+                    if let Some(idx) = self.card_at(col, row, state) {
+                        state.select(Some(idx));
+                    }
+                    return;
+                }
+                let inner = self.areas.list_inner();
+                if !hit(inner, col, row) {
+                    return;
+                }
+                if let Some(idx) = self.item_at(row, state) {
+                    state.select(Some(idx));
+                    // The checkbox follows the marker on the middle row.
+                    let on_checkbox = self.theme.checkbox_columns().contains(&(col - inner.x))
+                        && (row - inner.y) % 3 == 1;
+                    if on_checkbox {
+                        self.toggle_selection(state);
+                    }
+                }
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.dragging => {
+                let idx = self.input_index_at(col);
+                self.inputter.drag_to(idx);
+            }
+            MouseEventKind::Up(MouseButton::Left) => {
+                self.dragging = false;
+                // Only activate if released over the same button it was pressed on.
+                if let Some(i) = self.pressed.take() {
+                    if self.button_at(col, row) == Some(i) {
+                        self.button = i;
+                        self.press_button(i, state);
+                    }
                 }
             }
+            MouseEventKind::Moved => self.hover = self.button_at(col, row),
+            MouseEventKind::ScrollDown if self.board && hit(self.areas.list, col, row) => self.select_card(Action::Down, state),
+            MouseEventKind::ScrollUp if self.board && hit(self.areas.list, col, row) => self.select_card(Action::Up, state),
+            MouseEventKind::ScrollDown if hit(self.areas.list, col, row) => self.scroll_list(1, state),
+            MouseEventKind::ScrollUp if hit(self.areas.list, col, row) => self.scroll_list(-1, state),
+            _ => {}
         }
-        self.todolist = new_list;
-        state.select(if sel_cleared { Some(self.todolist.len() - 1) } else { Some(new_sel) });
     }
 
-    /// Toggle completion of current selection, if any.
-    fn toggle_selection(&mut self, state: &mut ListState) {
-        if let Some(sel) = state.selected() {
-            self.todolist[sel].toggle();
+    /// Replace the input with recalled text, with the cursor at the end.
+    fn recall(&mut self, text: Option<String>) {
+        if let Some(text) = text {
+            self.inputter.input = text;
+            self.inputter.cursor_to_end();
+            self.inputter.render_placeholder = self.inputter.input.is_empty();
         }
     }
 
-    /// Update selection by a given offset clamped to 0 and max list index.
-    fn select_offset(&mut self, offset: i16, state: &mut ListState) {
-        #[allow(clippy::cast_possible_truncation)]
-        #[allow(clippy::cast_possible_wrap)]
-        if let Some(sel) = state.selected() {
-            let mut new = (sel as i16) + offset;
-            let max = (self.todolist.len() - 1) as i16;
-            let min: i16 = 0;
-            new = new.clamp(min, max);
-            #[allow(clippy::cast_sign_loss)]
-            state.select(Some(new as usize));
-        }
+    /// Start a reverse search, or find the next older match when searching.
+    fn search_history(&mut self) {
+        let Some((query, found)) = self.input_history.search.take() else {
+            self.input_history.draft = self.inputter.input.clone();
+            self.input_history.search = Some((String::new(), None));
+            return;
+        };
+        let older = self.input_history.find(&query, found.unwrap_or(usize::MAX));
+        let text = older.map(|i| self.input_history.entries[i].clone());
+        self.input_history.search = Some((query, older.or(found)));
+        self.recall(text);
     }
 
-    /// Discard current input and stop editing.
-    fn cancel_edit(&mut self) {
-        self.inputter.restore();
-        self.editing = None;
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        let Some((mut query, found)) = self.input_history.search.take() else {
+            return;
+        };
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('r')) => {
+                self.input_history.search = Some((query, found));
+                return self.search_history();
+            }
+            (KeyModifiers::CONTROL, KeyCode::Char('g')) | (_, KeyCode::Esc) => {
+                let draft = self.input_history.draft.clone();
+                return self.recall(Some(draft));
+            }
+            (KeyModifiers::NONE | KeyModifiers::SHIFT, KeyCode::Char(c)) => query.push(c),
+            (_, KeyCode::Backspace) => {
+                query.pop();
+            }
+            // Anything else accepts the match and leaves it for editing.
+            _ => return,
+        }
+        // Typing keeps the current match if it still matches, deleting
+        // searches again from the newest.
+        let before = match (key.code, found) {
+            (KeyCode::Char(_), Some(idx)) => idx + 1,
+            _ => usize::MAX,
+        };
+        let found = self.input_history.find(&query, before);
+        let text = found.map(|i| self.input_history.entries[i].clone());
+        self.input_history.search = Some((query, found));
+        self.recall(text);
     }
 
-    /// Save current input and take over input for editing.
-    fn begin_editing(&mut self, state: &ListState) {
-        if let Some(sel) = state.selected() {
-            self.inputter.save();
-            self.focus = Focus::Input;
-            self.editing = Some(sel);
-            self.inputter.input = self.todolist[sel].name.clone();
-            self.inputter.cursor = self.inputter.input.chars().count();
+    /// Refresh completions once the word before the cursor has changed, so
+    /// that dismissed ones stay hidden until then.
+    fn update_completion(&mut self) {
+        let (input, end) = (&self.inputter.input, self.inputter.byte_index());
+        let names: Vec<&str> = self.lists.iter().map(|(name, _)| name.as_str()).collect();
+        let completion = match self.prompt {
+            Some(Prompt::Move | Prompt::Switch) => Completion::lists(input, end, &names),
+            _ => Completion::new(input, end, &self.todolist, &[&[self.list.as_str()], &names[..]].concat()),
+        };
+        if (completion.start, &completion.prefix) != (self.completion.start, &self.completion.prefix) {
+            self.completion = completion;
         }
     }
 
-    fn new_item(&mut self, name: String, state: &mut ListState) {
-        self.todolist.push(Todo::new(name));
-        state.select(Some(self.todolist.len() - 1));
-        self.first_todo = false;
-        self.inputter.reset();
+    /// Navigate, accept or dismiss completions, if there are any.
+    fn handle_completion_key(&mut self, key: KeyEvent) -> bool {
+        if self.completion.items.is_empty() {
+            return false;
+        }
+        match key.code {
+            KeyCode::Tab     => self.completion.cycle(true),
+            KeyCode::BackTab => self.completion.cycle(false),
+            KeyCode::Esc     => self.completion.items.clear(),
+            KeyCode::Enter => {
+                let Some(i) = self.completion.selected else {
+                    return false;
+                };
+                let start = self.inputter.input[..self.completion.start].graphemes(true).count();
+                self.inputter.anchor = Some(start);
+                self.inputter.insert_str(&format!("{} ", self.completion.items[i]));
+            }
+            _ => return false,
+        }
+        true
     }
 
-    /// Save edits and restore input.
-    fn finish_editing(&mut self, name: String, idx: usize) {
-        self.todolist[idx].name = name;
-        self.focus = Focus::List;
-        self.editing = None;
-        self.inputter.restore();
+    /// Rest of the most recent completed todo name that starts with the
+    /// input, suggested fish-style while the cursor is at the end.
+    fn suggestion(&self) -> Option<&str> {
+        let input = self.inputter.input.as_str();
+        if input.is_empty() || self.inputter.cursor != self.inputter.len() || !self.completion.items.is_empty() {
+            return None;
+        }
+        self.todolist.iter().rev()
+            .filter(|t| t.complete)
+            .find_map(|t| t.name.strip_prefix(input).filter(|rest| !rest.is_empty()))
     }
 
-    /// Draw to frame using pre-initialized `ListState` and the bindings widget.
-    fn draw(&self, frame: &mut Frame, liststate: &mut ListState, bindings_widget: &Paragraph) {
-        let full = frame.size();
-        let margin_side = 30;
-        let list_top = 13;
-        let list_bot = 4;
-        let width = full.width - margin_side - margin_side;
+    fn handle_input_key(&mut self, key: KeyEvent, action: Option<Action>, vim: bool, state: &mut ListState) {
+        if self.input_history.search.is_some() {
+            return self.handle_search_key(key);
+        }
+        if vim && self.handle_vim_input_key(key) {
+            return;
+        }
 
-        let header = Paragraph::new("T O D O M V C").alignment(Alignment::Center);
-        frame.render_widget(&header, Rect::new(0, 5, full.width - 1, 1));
+        if let Some(rest) = self.suggestion().filter(|_| matches!(action, Some(Action::CharRight | Action::LineEnd))) {
+            let rest = rest.to_string();
+            return self.inputter.insert_str(&rest);
+        }
+        match (action, key.code) {
+            (Some(action), _) => self.perform(action, state),
+            (None, KeyCode::Char(c)) if (key.modifiers - KeyModifiers::SHIFT).is_empty() => self.inputter.insert(c),
+            _ => {}
+        }
+    }
 
-        let input_text = Line::from(
-            if self.inputter.render_placeholder && self.editing.is_none() {
-                "What needs to be done?".dark_gray()
-            } else {
-                self.inputter.input.clone().into()
-            }
-        );
-        let input_widget = Paragraph::new(input_text).block(
-            Block::bordered()
-                .border_type(BorderType::Rounded)
-                .padding(Padding::horizontal(1))
-                .border_style(self.get_border(&Focus::Input))
-        );
-        frame.render_widget(input_widget, Rect::new(margin_side, 9, width, 3));
-        if self.focus == Focus::Input {
-            let input_cursor_absolute = margin_side + 2 + u16::try_from(self.inputter.cursor).unwrap();
-            frame.set_cursor(input_cursor_absolute, 10);
+    /// Insert a single line paste into the input in one step, or offer to
+    /// create a todo for each line of a multi-line paste.
+    fn handle_paste(&mut self, text: &str) {
+        if self.pending_paste.is_some() {
+            return;
+        }
+        self.inputter.next_command();
+        let todos = parse_pasted(text, now());
+        if todos.len() > 1 && self.editing.is_none() && self.prompt.is_none() {
+            self.pending_paste = Some(todos);
+            return;
         }
+        self.focus = Focus::Input;
+        let line = text.trim_matches(['\r', '\n']).replace(['\r', '\n'], " ");
+        self.inputter.insert_str(&line);
+        self.update_completion();
+    }
 
-        match self.focus {
-            Focus::Input => {
-                let editing_hint = vec![
-                    "enter".bold(), ": save, ".into(),
-                    "esc".bold(), ": cancel ".into()
-                ];
-                let input_hint = vec!["enter".bold(), ": save ".into()];
-                frame.render_widget(
-                    Paragraph::new(Line::from(if self.editing.is_some() { editing_hint } else { input_hint }))
-                        .alignment(Alignment::Right),
-                    Rect::new(margin_side, list_top - 1, width, 1)
-                );
-            },
-            Focus::List => {
-                let buttons = Line::from(vec![
-                    " (M)".bold(), " Mark all as complete  ".into(),
-                    "(C)".bold(), " Clear completed".into()
-                ]);
-                let buttons_width = usize::try_into(buttons.width()).unwrap();
-                frame.render_widget(
-                    Paragraph::new(buttons),
-                    Rect::new(margin_side, list_top - 1, buttons_width, 1)
-                );
-            }
-        };
-
-        let todolist = self.todolist.iter().map(Todo::fmt_item).collect::<List>()
-            .block(Block::bordered()
-                .border_type(BorderType::Rounded)
-                .border_style(self.get_border(&Focus::List)))
-            .highlight_style(Style::default().white().bg(Color::Rgb(65, 70, 80)));
-
-        frame.render_stateful_widget(
-            &todolist,
-            Rect::new(
-                margin_side,
-                list_top,
-                width,
-                full.height - list_top - list_bot,
-            ),
-            liststate,
-        );
+    /// Create todos from the pending paste, as a single undo step.
+    fn confirm_paste(&mut self, state: &mut ListState) {
+        if let Some(todos) = self.pending_paste.take() {
+            self.checkpoint(state);
+            self.todolist.extend(todos);
+            state.select(Some(self.todolist.len() - 1));
+            self.first_todo = false;
+        }
+    }
 
-        let itemsleft = Paragraph::new(
-            if self.first_todo {
-                String::new()
-            } else {
-                fmt_itemsleft(&self.todolist)
+    /// Range of up to `n` items from the selection.
+    fn selection_items(&self, n: usize, state: &ListState) -> Option<Vec<usize>> {
+        let pos = self.position(state)?;
+        Some(self.order().into_iter().skip(pos).take(n).collect())
+    }
+
+    /// Make a repeatable change `n` times, or to `n` items.
+    fn vim_change(&mut self, change: Change, n: usize, state: &mut ListState) {
+        match change {
+            Change::Delete => {
+                let (Some(items), Some(pos)) = (self.selection_items(n, state), self.position(state)) else {
+                    return;
+                };
+                self.checkpoint(state);
+                self.vim.register = items.iter().map(|&i| self.todolist[i].clone()).collect();
+                let mut items = items;
+                items.sort_unstable();
+                for i in items.into_iter().rev() {
+                    self.todolist.remove(i);
+                }
+                state.select(None);
+                self.select_index(pos, state);
             }
-        ).alignment(Alignment::Right);
-        frame.render_widget(&itemsleft, Rect::new(margin_side, full.height - list_bot, width, 1));
+            Change::Put { before } => {
+                if self.vim.register.is_empty() {
+                    return;
+                }
+                let idx = match state.selected() {
+                    Some(sel) if !before => sel + 1,
+                    Some(sel) => sel,
+                    None => self.todolist.len(),
+                };
+                self.checkpoint(state);
+                let items: Todos = (0..n).flat_map(|_| self.vim.register.clone()).collect();
+                self.todolist.splice(idx..idx, items);
+                state.select(Some(idx));
+                self.first_todo = false;
+            }
+            Change::Toggle => {
+                let Some(items) = self.selection_items(n, state) else { return };
+                self.checkpoint(state);
+                for i in items {
+                    self.todolist[i].toggle();
+                }
+            }
+        }
+        self.vim.last_change = Some((n, change));
+    }
 
-        frame.render_widget(bindings_widget, Rect::new(0, full.height - 1, full.width - 1, 1));
+    /// Yank `n` items from the selection, also copying their names.
+    fn vim_yank(&mut self, n: usize, state: &ListState) {
+        if let Some(items) = self.selection_items(n, state) {
+            self.vim.register = items.iter().map(|&i| self.todolist[i].clone()).collect();
+            let names: Vec<&str> = self.vim.register.iter().map(|t| t.name.as_str()).collect();
+            self.clipboard.copy(&names.join("\n"));
+        }
     }
 
-    fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result<()> {
-        terminal.show_cursor()?;
+    /// Open a new todo below, or above, the selection.
+    fn vim_open(&mut self, above: bool, state: &ListState) {
+        self.insert_at = Some(match state.selected() {
+            Some(sel) if above => sel,
+            Some(sel) => sel + 1,
+            None => self.todolist.len(),
+        });
+        self.focus = Focus::Input;
+        self.vim.mode = Mode::Insert;
+    }
 
-        let mut liststate = ListState::default();
+    /// Handle a key in list focus with vim bindings, returning whether it
+    /// was used. Keys not used here fall back to the usual bindings.
+    fn handle_vim_list_key(&mut self, key: KeyEvent, state: &mut ListState) -> bool {
+        let KeyCode::Char(c) = key.code else {
+            self.vim.reset();
+            return false;
+        };
+        if !(key.modifiers - KeyModifiers::SHIFT).is_empty() {
+            return false;
+        }
+        if self.vim.push_count(c) {
+            return true;
+        }
+        let count = self.vim.count.take();
+        let n = count.unwrap_or(1);
+        let offset = isize::try_from(n).unwrap_or(isize::MAX);
+        match (self.vim.pending.take(), c) {
+            (Some('d'), 'd') => self.vim_change(Change::Delete, n, state),
+            (Some('y'), 'y') => self.vim_yank(n, state),
+            (Some('g'), 'g') => self.select_index(n - 1, state),
+            // Anything else cancels the operator.
+            (Some(_), _) => {}
+            (None, 'd' | 'y' | 'g') => {
+                self.vim.pending = Some(c);
+                self.vim.count = count;
+            }
+            (None, 'j') => self.select_offset(offset, state),
+            (None, 'k') => self.select_offset(-offset, state),
+            (None, 'G') => self.select_index(count.map_or(usize::MAX, |n| n - 1), state),
+            (None, 'p') => self.vim_change(Change::Put { before: false }, n, state),
+            (None, 'P') => self.vim_change(Change::Put { before: true }, n, state),
+            // Marked todos are toggled as usual.
+            (None, ' ') if !self.todolist.iter().any(|t| t.marked) => self.vim_change(Change::Toggle, n, state),
+            (None, 'o') => self.vim_open(false, state),
+            (None, 'O') => self.vim_open(true, state),
+            (None, '.') => {
+                if let Some((last_n, change)) = self.vim.last_change {
+                    self.vim_change(change, count.unwrap_or(last_n), state);
+                }
+            }
+            _ => return false,
+        }
+        true
+    }
 
-        let bindings = [
-            ("tab", "switch focus"),
-            ("arrows", "navigate list"),
-            ("space/enter", "toggle complete"),
-        ];
-        let mut bindings_line = vec!["ctrl-c".bold(), ": quit".into()];
-        for pair in bindings {
-            bindings_line.push(", ".into());
-            bindings_line.push(pair.0.bold());
-            bindings_line.push(": ".into());
-            bindings_line.push(pair.1.into());
-        }
-        let bindings_widget =
-            Paragraph::new(Line::from(bindings_line)).alignment(Alignment::Center);
+    /// Handle a key in the input with vim bindings, returning whether it
+    /// was used. Keys not used here fall back to the usual bindings.
+    fn handle_vim_input_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers != KeyModifiers::NONE && key.modifiers != KeyModifiers::SHIFT {
+            return false;
+        }
+        let visual = self.vim.mode == Mode::Visual;
+        let c = match (self.vim.mode, key.code) {
+            (Mode::Insert, KeyCode::Esc) => {
+                self.vim.mode = Mode::Normal;
+                return true;
+            }
+            (Mode::Visual, KeyCode::Esc) => {
+                self.vim.mode = Mode::Normal;
+                self.inputter.anchor = None;
+                return true;
+            }
+            (Mode::Normal | Mode::Visual, KeyCode::Char(c)) => c,
+            _ => return false,
+        };
+        if self.vim.push_count(c) {
+            return true;
+        }
+        let n = self.vim.count.take().unwrap_or(1);
+        let inp = &mut self.inputter;
 
-        // Main loop
-        while !self.exit {
-            terminal.draw(|frame| self.draw(frame, &mut liststate, &bindings_widget))?;
+        let motion = match c {
+            'h' => Some(inp.cursor.saturating_sub(n)),
+            'l' => Some(inp.cursor.saturating_add(n)),
+            '0' | '^' => Some(0),
+            '$' => Some(inp.len()),
+            'b' => Some((0..n).fold(inp.cursor, |at, _| inp.word_start(at, is_word))),
+            'w' => Some((0..n).fold(inp.cursor, |at, _| inp.next_word_start(at))),
+            'e' => Some((0..n).fold(inp.cursor, |at, _| inp.word_end(at))),
+            _ => None,
+        };
 
-            // Blocks until there's an event. I think.
-            if let Event::Key(key_event) = event::read()? {
-                self.handle_key(key_event, &mut liststate);
+        let pending = self.vim.pending.take();
+        if let (None, Some(to)) = (pending, motion) {
+            if visual { inp.drag_to(to) } else { inp.set_cursor(to) }
+            return true;
+        }
+
+        match (pending, c) {
+            (Some('d'), 'd') => inp.kill(0, inp.len()),
+            (Some('c'), 'c') => {
+                inp.kill(0, inp.len());
+                self.vim.mode = Mode::Insert;
+            }
+            (Some('y'), 'y') => self.clipboard.copy(&inp.input),
+            (Some(op @ ('d' | 'c')), _) => {
+                // Like vim, `cw` changes to the end of the word, keeping the space after it.
+                let motion = if op == 'c' && c == 'w' {
+                    Some((0..n).fold(inp.cursor, |at, _| inp.word_end(at)))
+                } else {
+                    motion
+                };
+                if let Some(to) = motion {
+                    inp.kill(inp.cursor.min(to), inp.cursor.max(to));
+                    if op == 'c' {
+                        self.vim.mode = Mode::Insert;
+                    }
+                }
+            }
+            (Some(_), _) => {}
+            (None, 'd' | 'x') if visual => {
+                inp.delete_selection();
+                self.vim.mode = Mode::Normal;
+            }
+            (None, 'c' | 's') if visual => {
+                inp.delete_selection();
+                self.vim.mode = Mode::Insert;
+            }
+            (None, 'y') if visual => {
+                if let Some(text) = inp.selected_text() {
+                    self.clipboard.copy(text);
+                }
+                inp.anchor = None;
+                self.vim.mode = Mode::Normal;
+            }
+            (None, 'v') if visual => {
+                inp.anchor = None;
+                self.vim.mode = Mode::Normal;
+            }
+            (None, 'v') => {
+                inp.anchor = Some(inp.cursor);
+                self.vim.mode = Mode::Visual;
+            }
+            (None, 'd' | 'c' | 'y') => self.vim.pending = Some(c),
+            (None, 'x') => inp.kill(inp.cursor, (inp.cursor + n).min(inp.len())),
+            (None, 'X') => inp.kill(inp.cursor.saturating_sub(n), inp.cursor),
+            (None, 'D') => inp.kill_to_end(),
+            (None, 'C') => {
+                inp.kill_to_end();
+                self.vim.mode = Mode::Insert;
+            }
+            (None, 'p') => {
+                inp.right();
+                self.handle_paste(&self.clipboard.register.clone());
+            }
+            (None, 'P') => self.handle_paste(&self.clipboard.register.clone()),
+            (None, 'i') => self.vim.mode = Mode::Insert,
+            (None, 'a') => {
+                inp.right();
+                self.vim.mode = Mode::Insert;
             }
+            (None, 'I') => {
+                inp.cursor_to_start();
+                self.vim.mode = Mode::Insert;
+            }
+            (None, 'A') => {
+                inp.cursor_to_end();
+                self.vim.mode = Mode::Insert;
+            }
+            _ => {}
         }
-        Ok(())
+        // Characters never get typed outside of insert mode.
+        true
     }
 
-    fn handle_key(&mut self, key: KeyEvent, state: &mut ListState) {
-        if key.kind == KeyEventKind::Press {
-            if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL {
-                self.exit = true;
+    /// Indices of todos matching a search, in the order shown, with their
+    /// scores and the indices of matched chars.
+    fn search_matches(&self, query: &str) -> Vec<(usize, i64, Vec<usize>)> {
+        self.order().into_iter()
+            .filter_map(|i| fuzzy_match(query, &self.todolist[i].search_text()).map(|(score, m)| (i, score, m)))
+            .collect()
+    }
+
+    fn begin_search(&mut self, state: &ListState) {
+        self.search = Some(Search {
+            query: String::new(),
+            typing: true,
+            previous: (state.selected(), state.offset()),
+        });
+    }
+
+    /// Select the next or previous match after the selection, wrapping around.
+    fn select_match(&mut self, forward: bool, state: &mut ListState) {
+        let Some(search) = &self.search else {
+            return;
+        };
+        let order = self.order();
+        let found: Vec<usize> = self.search_matches(&search.query).into_iter()
+            .filter_map(|(i, ..)| order.iter().position(|&j| j == i))
+            .collect();
+        let pos = self.position(state);
+        let next = if forward {
+            found.iter().find(|&&p| pos.is_none_or(|s| p > s)).or(found.first())
+        } else {
+            found.iter().rev().find(|&&p| pos.is_none_or(|s| p < s)).or(found.last())
+        };
+        if let Some(&p) = next {
+            state.select(Some(order[p]));
+        }
+    }
+
+    /// Type a search query, with the list filtered to matches.
+    fn handle_list_search_key(&mut self, key: KeyEvent, state: &mut ListState) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        let ctrl = key.modifiers == KeyModifiers::CONTROL;
+        // Backspace on an empty query also cancels, restoring the selection.
+        if key.code == KeyCode::Esc || (key.code == KeyCode::Backspace && search.query.is_empty()) {
+            let (selected, offset) = search.previous;
+            state.select(selected);
+            *state.offset_mut() = offset;
+            self.search = None;
+            return;
+        }
+        match key.code {
+            KeyCode::Enter => {
+                search.typing = false;
+                if state.selected().is_none() {
+                    state.select(search.previous.0);
+                }
+                if search.query.is_empty() {
+                    self.search = None;
+                }
                 return;
             }
-
-            if key.code == KeyCode::Tab {
-                self.focus = match self.focus {
-                    Focus::Input => Focus::List,
-                    Focus::List => Focus::Input,
+            KeyCode::Backspace => {
+                search.query.pop();
+            }
+            KeyCode::Char(c) if !ctrl => search.query.push(c),
+            KeyCode::Up | KeyCode::Char('p') | KeyCode::Down | KeyCode::Char('n') => {
+                let query = search.query.clone();
+                let found = self.search_matches(&query);
+                let pos = found.iter().position(|(i, ..)| Some(*i) == state.selected());
+                let pos = match (key.code, pos) {
+                    (KeyCode::Up | KeyCode::Char('p'), Some(p)) => p.saturating_sub(1),
+                    (_, Some(p)) => (p + 1).min(found.len().saturating_sub(1)),
+                    (_, None) => 0,
                 };
+                if let Some((i, ..)) = found.get(pos) {
+                    state.select(Some(*i));
+                }
                 return;
             }
+            _ => return,
+        }
+        // Select the best match for the new query.
+        let query = search.query.clone();
+        let best = self.search_matches(&query).into_iter().rev().max_by_key(|(_, score, _)| *score);
+        state.select(best.map(|(i, ..)| i));
+    }
 
-            if self.focus == Focus::Input {
-                if key.modifiers == KeyModifiers::CONTROL {
-                    match key.code {
-                        KeyCode::Char('a') => self.inputter.cursor_to_start(),
-                        KeyCode::Char('e') => self.inputter.cursor_to_end(),
-                        _ => {}
-                    }
-                    return;
-                }
+    fn handle_key(&mut self, key: KeyEvent, state: &mut ListState) {
+        if key.kind == KeyEventKind::Press {
+            self.inputter.next_command();
+            // Copies instead when there is a selection in the input.
+            let copying = self.focus == Focus::Input && self.inputter.selection().is_some();
+            if self.keymap.get(Context::Global, key) == Some(Action::Quit) && !copying {
+                return self.perform(Action::Quit, state);
+            }
+
+            if self.pending_paste.is_some() {
                 match key.code {
-                    KeyCode::Char(c)   => self.inputter.insert(c),
-                    KeyCode::Left      => self.inputter.left(),
-                    KeyCode::Right     => self.inputter.right(),
-                    KeyCode::Backspace => self.inputter.delete_left(),
-                    KeyCode::Delete    => self.inputter.delete_right(),
-                    KeyCode::Enter => {
-                        let name = self.inputter.input.clone();
-                        if !name.is_empty() {
-                            if let Some(idx) = self.editing {
-                                self.finish_editing(name, idx);
-                            } else {
-                                self.new_item(name, state);
-                            }
-                        }
-                    }
-                    KeyCode::Esc => {
-                        // Treat as tab if not currently editing.
-                        self.focus = Focus::List;
-                        if self.editing.is_some() {
-                            self.cancel_edit();
-                        }
-                    }
+                    KeyCode::Enter | KeyCode::Char('y') => self.confirm_paste(state),
+                    KeyCode::Esc   | KeyCode::Char('n') => self.pending_paste = None,
                     _ => {}
                 }
-
                 return;
             }
 
-            // Todolist
-            if self.todolist.is_empty() || key.modifiers != KeyModifiers::NONE {
-                return;
+            if self.search.as_ref().is_some_and(|s| s.typing) {
+                return self.handle_list_search_key(key, state);
+            }
+            if self.sort_menu.is_some() {
+                return self.handle_sort_menu_key(key);
+            }
+            if self.palette.is_some() {
+                return self.handle_palette_key(key, state);
+            }
+            if self.help.is_some() {
+                return self.handle_help_key(key);
             }
 
-            match key.code {
-                KeyCode::Down  | KeyCode::Char('j') => self.select_offset(1, state),
-                KeyCode::Up    | KeyCode::Char('k') => self.select_offset(-1, state),
-                KeyCode::Enter | KeyCode::Char(' ') => self.toggle_selection(state),
-                KeyCode::Char('e') => self.begin_editing(state),
-                KeyCode::Char('m') => self.complete_all(),
-                KeyCode::Char('c') => self.clear_completed(state),
-                _ => {}
+            if self.focus == Focus::Input && self.handle_completion_key(key) {
+                return self.update_completion();
+            }
+
+            // Global bindings, unless the focused widget binds the key itself.
+            // Vim keys come before the others, except those the config binds.
+            let context = self.context();
+            let mut keys = self.pending_keys.clone();
+            keys.push(chord(key));
+            let (action, vim) = match self.keymap.resolve(&mut self.pending_keys, context, key) {
+                Bound::Action(Context::Global, action) => return self.perform(action, state),
+                Bound::Action(c, action) => {
+                    let default = Keymap::default().bindings.contains(&(c, keys, action));
+                    (Some(action), self.vim.enabled && default)
+                }
+                Bound::Pending => return,
+                Bound::Unbound => (None, self.vim.enabled),
+            };
+
+            match self.focus {
+                Focus::Input => {
+                    self.handle_input_key(key, action, vim, state);
+                    self.update_completion();
+                }
+                Focus::Buttons => {
+                    if let Some(action) = action {
+                        self.perform(action, state);
+                    }
+                }
+                Focus::List => {
+                    if vim && !self.board && self.handle_vim_list_key(key, state) {
+                        return;
+                    }
+                    if let Some(action) = action {
+                        self.perform(action, state);
+                    }
+                }
             };
         }
     }
@@ -472,22 +4823,87 @@ impl App {
 
 // main ///////////////////////////////////////////////////////////////
 fn main() -> Result<()> {
-    stdout().execute(EnterAlternateScreen)?;
+    // Resolve a date as due dates would be, e.g. `--parse-date "tomorrow 9am"`.
+    let args: Vec<String> = env::args().collect();
+    if let Some(i) = args.iter().position(|arg| arg == "--parse-date") {
+        let text = args.get(i + 1).map_or("", String::as_str);
+        match parse_date(text, now()) {
+            Some(due) => println!("{}", due.iso()),
+            None => {
+                eprintln!("could not parse date: {text:?}");
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
+    // Report every problem with the config before taking over the screen.
+    let config_path = config_dir().map(|dir| dir.join("config.toml"));
+    let config = match Config::load(config_path.clone()) {
+        Ok(config) => config,
+        Err(errors) => {
+            let path = config_path.unwrap_or_default();
+            for error in errors {
+                eprintln!("{}: {error}", path.display());
+            }
+            std::process::exit(1);
+        }
+    };
+
+    stdout()
+        .execute(EnterAlternateScreen)?
+        .execute(EnableMouseCapture)?
+        .execute(EnableBracketedPaste)?;
     let res = enable_raw_mode();
 
     if res.is_err() {
         // Ensure terminal is restored if entering raw mode fails
-        stdout().execute(LeaveAlternateScreen)?;
+        stdout()
+            .execute(DisableBracketedPaste)?
+            .execute(DisableMouseCapture)?
+            .execute(LeaveAlternateScreen)?;
         res
     } else {
         let res = Terminal::new(CrosstermBackend::new(stdout()));
         let mut app_result = Ok(());
         if let Ok(mut terminal) = res {
-            app_result = App::new().run(&mut terminal);
+            let mut app = App::new();
+            app.keymap = config.keymap;
+            // NO_COLOR counts only when set to something, see https://no-color.org.
+            let monochrome = args.iter().any(|arg| arg == "--monochrome")
+                || env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+            let ascii = args.iter().any(|arg| arg == "--ascii");
+            let support = ColorSupport::detect(env::var("COLORTERM").ok().as_deref(), env::var("TERM").ok().as_deref());
+            let adapt = |theme: Theme| {
+                let theme = if ascii { theme.ascii() } else { theme };
+                if monochrome { theme.monochrome() } else { theme.downsample(support) }
+            };
+            // Keys typed while the terminal is asked for its background.
+            let mut typed = Vec::new();
+            let theme = config.theme.unwrap_or_else(|| {
+                let light = !monochrome && {
+                    let (background, keys) = query_background(Duration::from_millis(200));
+                    typed = keys;
+                    light_background(background)
+                };
+                let name = if light { "light" } else { "default" };
+                config.themes.iter().find(|t| t.name == name).cloned().unwrap_or_default()
+            });
+            app.themes = config.themes.into_iter().map(adapt).collect();
+            app.theme = adapt(theme);
+            app.vim.enabled = args.iter().any(|arg| arg == "--vim");
+            app.input_history = InputHistory::load(data_dir().map(|dir| dir.join("history")));
+            app.sort_modes = SortModes::load(data_dir().map(|dir| dir.join("sort")));
+            app.sort = app.sort_modes.get(DEFAULT_LIST);
+            app.collator = collator(&collation_locale());
+            app_result = app.run(&mut terminal, typed);
         }
 
         let _ = disable_raw_mode();
-        stdout().execute(LeaveAlternateScreen)?;
+        stdout()
+            .execute(DisableBracketedPaste)?
+            .execute(DisableMouseCapture)?
+            .execute(LeaveAlternateScreen)?;
         app_result
     }
 }
@@ -1,6 +1,9 @@
 use super::*;
+use chrono::{NaiveDate, NaiveTime};
+use std::cmp::Ordering;
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn todo() {
     let mut t = Todo::new("name".to_string());
     assert_eq!("name".to_string(), t.name);
@@ -68,3 +71,1838 @@ fn inputs_saving() {
     assert_eq!(2, inp.cursor);
     assert_eq!("abc".to_string(), inp.input);
 }
+
+#[test]
+fn mouse() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.areas = Areas::new(Rect::new(0, 0, 100, 40));
+    app.new_item("1".to_string(), &mut state);
+    app.new_item("2".to_string(), &mut state);
+
+    let click = |column, row| MouseEvent {
+        kind: MouseEventKind::Down(MouseButton::Left),
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    };
+    let inner = app.areas.list_inner();
+
+    // Clicking a row selects it and focuses the list.
+    app.handle_mouse(click(inner.x + 10, inner.y + 1), &mut state);
+    assert_eq!(Focus::List, app.focus);
+    assert_eq!(Some(0), state.selected());
+    assert!(!app.todolist[0].complete);
+
+    // Clicking the checkbox toggles it.
+    app.handle_mouse(click(inner.x + 4, inner.y + 4), &mut state);
+    assert_eq!(Some(1), state.selected());
+    assert!(app.todolist[1].complete);
+
+    // Clicking past the last item does nothing.
+    app.handle_mouse(click(inner.x + 4, inner.y + 7), &mut state);
+    assert_eq!(Some(1), state.selected());
+
+    app.handle_mouse(click(app.areas.input.x + 1, app.areas.input.y + 1), &mut state);
+    assert_eq!(Focus::Input, app.focus);
+
+    // Overlays keep clicks from reaching the list under them.
+    app.help = Some(0);
+    app.handle_mouse(click(inner.x + 4, inner.y + 4), &mut state);
+    app.help = None;
+    app.palette = Some(Palette::default());
+    app.handle_mouse(click(inner.x + 4, inner.y + 4), &mut state);
+    app.palette = None;
+    app.sort_menu = Some(0);
+    app.handle_mouse(click(inner.x + 4, inner.y + 1), &mut state);
+    app.sort_menu = None;
+    app.pending_paste = Some(Vec::new());
+    app.handle_mouse(click(inner.x + 4, inner.y + 1), &mut state);
+    assert_eq!(Focus::Input, app.focus);
+    assert_eq!(Some(1), state.selected());
+    assert!(app.todolist[1].complete);
+    app.pending_paste = None;
+
+    // The wheel scrolls the list as far as the last page, and moves the
+    // selection only to keep it in view.
+    for name in 3..=20 {
+        app.new_item(name.to_string(), &mut state);
+    }
+    let page = app.areas.list_page();
+    let scroll = |kind| MouseEvent { kind, column: inner.x + 4, row: inner.y + 1, modifiers: KeyModifiers::NONE };
+    state.select(Some(page - 1));
+    app.handle_mouse(scroll(MouseEventKind::ScrollDown), &mut state);
+    assert_eq!((1, Some(page - 1)), (state.offset(), state.selected()));
+    app.handle_mouse(scroll(MouseEventKind::ScrollUp), &mut state);
+    app.handle_mouse(scroll(MouseEventKind::ScrollUp), &mut state);
+    assert_eq!((0, Some(page - 1)), (state.offset(), state.selected()));
+    for _ in 0..30 {
+        app.handle_mouse(scroll(MouseEventKind::ScrollDown), &mut state);
+    }
+    assert_eq!((20 - page, Some(20 - page)), (state.offset(), state.selected()));
+}
+
+#[test]
+fn inputs_selection() {
+    let mut inp = Inputter::new();
+    for c in "hello big world".chars() {
+        inp.insert(c);
+    }
+
+    inp.select_word(7);
+    assert_eq!(Some((6, 9)), inp.selection());
+    inp.insert('X');
+    assert_eq!("hello X world".to_string(), inp.input);
+    assert_eq!(7, inp.cursor);
+
+    // Drag backwards from the end.
+    inp.select_from(13);
+    inp.drag_to(8);
+    assert_eq!(Some((8, 13)), inp.selection());
+    inp.delete_left();
+    assert_eq!("hello X ".to_string(), inp.input);
+    assert_eq!(8, inp.cursor);
+    assert_eq!(None, inp.selection());
+
+    // An empty selection behaves as a plain cursor.
+    inp.select_from(0);
+    inp.delete_right();
+    assert_eq!("ello X ".to_string(), inp.input);
+}
+
+#[test]
+fn mouse_input() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.areas = Areas::new(Rect::new(0, 0, 100, 40));
+    for c in "one two".chars() {
+        app.inputter.insert(c);
+    }
+    let input = app.areas.input;
+    let mouse = |kind, column| MouseEvent {
+        kind,
+        column,
+        row: input.y + 1,
+        modifiers: KeyModifiers::NONE,
+    };
+
+    app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), input.x + 3), &mut state);
+    assert_eq!(1, app.inputter.cursor);
+    app.handle_mouse(mouse(MouseEventKind::Drag(MouseButton::Left), input.x + 7), &mut state);
+    app.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left), input.x + 7), &mut state);
+    assert_eq!(Some((1, 5)), app.inputter.selection());
+
+    // Double-click selects a word.
+    app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), input.x + 8), &mut state);
+    app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left), input.x + 8), &mut state);
+    assert_eq!(Some((4, 7)), app.inputter.selection());
+}
+
+#[test]
+fn buttons() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.areas = Areas::new(Rect::new(0, 0, 120, 40));
+    app.new_item("1".to_string(), &mut state);
+    app.new_item("2".to_string(), &mut state);
+
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    app.handle_key(key(KeyCode::Tab), &mut state);
+    assert_eq!(Focus::Buttons, app.focus);
+    app.handle_key(key(KeyCode::Enter), &mut state);
+    assert!(app.todolist.iter().all(|t| t.complete));
+
+    // Pressing and releasing the mouse on "Clear completed" clears everything.
+    let area = app.areas.buttons[1];
+    let mouse = |kind| MouseEvent { kind, column: area.x + 1, row: area.y, modifiers: KeyModifiers::NONE };
+    app.handle_mouse(mouse(MouseEventKind::Down(MouseButton::Left)), &mut state);
+    assert_eq!(Some(1), app.pressed);
+    app.handle_mouse(mouse(MouseEventKind::Up(MouseButton::Left)), &mut state);
+    assert_eq!(None, app.pressed);
+    assert!(app.todolist.is_empty());
+    assert_eq!(None, state.selected());
+
+    app.handle_key(key(KeyCode::BackTab), &mut state);
+    assert_eq!(Focus::Input, app.focus);
+}
+
+#[test]
+fn navigation() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    // 40 rows leaves room for 7 items in the list.
+    app.areas = Areas::new(Rect::new(0, 0, 100, 40));
+    assert_eq!(7, app.page_size());
+    for i in 0..40_000 {
+        app.todolist.push(Todo::new(i.to_string()));
+    }
+    state.select(Some(0));
+    app.focus = Focus::List;
+
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    app.handle_key(key(KeyCode::PageDown, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(7), state.selected());
+    app.handle_key(key(KeyCode::Char('u'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(Some(3), state.selected());
+    app.handle_key(key(KeyCode::Char('d'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(Some(7), state.selected());
+
+    // Beyond i16::MAX items.
+    app.handle_key(key(KeyCode::Char('G'), KeyModifiers::SHIFT), &mut state);
+    assert_eq!(Some(39_999), state.selected());
+    app.handle_key(key(KeyCode::PageDown, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(39_999), state.selected());
+    app.handle_key(key(KeyCode::Up, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(39_998), state.selected());
+
+    app.handle_key(key(KeyCode::Home, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(0), state.selected());
+    app.handle_key(key(KeyCode::PageUp, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(0), state.selected());
+    app.handle_key(key(KeyCode::End, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(39_999), state.selected());
+    app.handle_key(key(KeyCode::Char('g'), KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(0), state.selected());
+}
+
+#[test]
+fn undo_redo() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    let names = |app: &App| app.todolist.iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+
+    app.new_item("1".to_string(), &mut state);
+    app.new_item("2".to_string(), &mut state);
+    app.new_item("3".to_string(), &mut state);
+    app.focus = Focus::List;
+    state.select(Some(1));
+    app.toggle_selection(&mut state);
+    app.handle_key(key(KeyCode::Char('c'), KeyModifiers::NONE), &mut state);
+    assert_eq!(vec!["1", "3"], names(&app));
+    assert_eq!(Some(1), state.selected());
+
+    // Undo the clear, restoring the selection too.
+    app.handle_key(key(KeyCode::Char('u'), KeyModifiers::NONE), &mut state);
+    assert_eq!(vec!["1", "2", "3"], names(&app));
+    assert!(app.todolist[1].complete);
+    assert_eq!(Some(1), state.selected());
+
+    app.handle_key(key(KeyCode::Char('z'), KeyModifiers::CONTROL), &mut state);
+    assert!(!app.todolist[1].complete);
+
+    app.handle_key(key(KeyCode::Char('r'), KeyModifiers::CONTROL), &mut state);
+    app.handle_key(key(KeyCode::Char('y'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(vec!["1", "3"], names(&app));
+    app.handle_key(key(KeyCode::Char('y'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(vec!["1", "3"], names(&app));
+
+    // Undo and redo work from the input too, leaving what is typed alone.
+    app.focus = Focus::Input;
+    app.inputter.insert_str("draft");
+    app.handle_key(key(KeyCode::Char('z'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(vec!["1", "2", "3"], names(&app));
+    app.handle_key(key(KeyCode::Char('y'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(vec!["1", "3"], names(&app));
+    assert_eq!("draft", app.inputter.input);
+    app.inputter.reset();
+    app.focus = Focus::List;
+
+    // Delete selects the previous item, and a new change clears redo.
+    app.handle_key(key(KeyCode::Backspace, KeyModifiers::NONE), &mut state);
+    assert_eq!(vec!["1"], names(&app));
+    assert_eq!(Some(0), state.selected());
+    app.undo(&mut state);
+    app.begin_editing(&state);
+    app.finish_editing("three".to_string(), 1, &state);
+    assert_eq!(vec!["1", "three"], names(&app));
+    app.redo(&mut state);
+    assert_eq!(vec!["1", "three"], names(&app));
+    app.undo(&mut state);
+    assert_eq!(vec!["1", "3"], names(&app));
+}
+
+#[test]
+fn inputs_unicode() {
+    let mut inp = Inputter::new();
+    // Wide CJK characters take two columns each.
+    for c in "你好".chars() {
+        inp.insert(c);
+    }
+    assert_eq!(2, inp.cursor);
+    assert_eq!(4, inp.width_to(inp.cursor));
+    assert_eq!(1, inp.index_at_width(2));
+    assert_eq!(1, inp.index_at_width(3));
+    inp.left();
+    inp.delete_right();
+    assert_eq!("你".to_string(), inp.input);
+    assert_eq!(1, inp.cursor);
+
+    // A combining accent joins the previous character.
+    inp.reset();
+    for c in "cafe\u{301}!".chars() {
+        inp.insert(c);
+    }
+    assert_eq!(5, inp.cursor);
+    assert_eq!(5, inp.width_to(inp.cursor));
+    inp.left();
+    inp.delete_left();
+    assert_eq!("caf!".to_string(), inp.input);
+    assert_eq!(3, inp.cursor);
+
+    // Emoji, including a modifier sequence, are deleted whole.
+    inp.reset();
+    for c in "a👍🏽b".chars() {
+        inp.insert(c);
+    }
+    assert_eq!(3, inp.len());
+    inp.set_cursor(1);
+    assert_eq!(1, inp.width_to(inp.cursor));
+    inp.delete_right();
+    assert_eq!("ab".to_string(), inp.input);
+
+    // Zero-width joiners don't add to the width.
+    inp.reset();
+    inp.insert('x');
+    inp.insert('\u{200b}');
+    assert_eq!(1, inp.width_to(inp.len()));
+}
+
+#[test]
+fn inputs_scrolling() {
+    let text = |line: Line| line.spans.iter().map(|s| s.content.to_string()).collect::<String>();
+    let mut inp = Inputter::new();
+    for c in "abcdefghijklmnop".chars() {
+        inp.insert(c);
+    }
+
+    // Cursor at the end: clipped on the left only.
+    inp.scroll_to_cursor(10);
+    assert_eq!(7, inp.scroll);
+    assert_eq!("…ijklmnop", text(inp.view(10, &[], Style::new().reversed(), &"…".into())));
+    assert_eq!(9, inp.width_to(inp.cursor) - inp.scroll);
+
+    // Moving to the start scrolls back, clipped on the right only.
+    inp.cursor_to_start();
+    inp.scroll_to_cursor(10);
+    assert_eq!(0, inp.scroll);
+    assert_eq!("abcdefghi…", text(inp.view(10, &[], Style::new().reversed(), &"…".into())));
+
+    // Cursor in the middle stays clear of both ellipses.
+    inp.set_cursor(12);
+    inp.scroll_to_cursor(10);
+    assert_eq!("…fghijklm…", text(inp.view(10, &[], Style::new().reversed(), &"…".into())));
+    assert_eq!(8, inp.width_to(inp.cursor) - inp.scroll);
+
+    // Text that fits is not scrolled, even after being long.
+    inp.restore();
+    inp.scroll_to_cursor(10);
+    assert_eq!(0, inp.scroll);
+    assert_eq!("", text(inp.view(10, &[], Style::new().reversed(), &"…".into())));
+
+    // Wide characters cut by an edge are padded.
+    inp.reset();
+    for c in "一二三四五六".chars() {
+        inp.insert(c);
+    }
+    inp.scroll_to_cursor(7);
+    assert_eq!(6, inp.scroll);
+    assert_eq!("… 五六", text(inp.view(7, &[], Style::new().reversed(), &"…".into())));
+}
+
+#[test]
+fn inputs_readline() {
+    let mut inp = Inputter::new();
+    inp.insert_str("foo-bar baz qux");
+    assert_eq!(15, inp.cursor);
+
+    inp.word_left();
+    assert_eq!(12, inp.cursor);
+    inp.word_left();
+    inp.word_left();
+    assert_eq!(4, inp.cursor);
+    inp.word_right();
+    assert_eq!(7, inp.cursor);
+    inp.word_right();
+    assert_eq!(11, inp.cursor);
+
+    // Alt-d, then yank it back.
+    inp.kill_word_right();
+    assert_eq!("foo-bar baz".to_string(), inp.input);
+    inp.yank();
+    assert_eq!("foo-bar baz qux".to_string(), inp.input);
+    assert_eq!(15, inp.cursor);
+
+    // Ctrl-w only stops at whitespace, alt-backspace at any non-word.
+    inp.set_cursor(7);
+    inp.kill_unix_word_left();
+    assert_eq!(" baz qux".to_string(), inp.input);
+    inp.yank();
+    inp.kill_word_left();
+    assert_eq!("foo- baz qux".to_string(), inp.input);
+    assert_eq!(4, inp.cursor);
+
+    inp.kill_to_end();
+    assert_eq!("foo-".to_string(), inp.input);
+    inp.cursor_to_start();
+    inp.yank();
+    assert_eq!(" baz quxfoo-".to_string(), inp.input);
+    assert_eq!(8, inp.cursor);
+    inp.kill_to_start();
+    assert_eq!("foo-".to_string(), inp.input);
+    assert_eq!(0, inp.cursor);
+
+    // Ctrl-t swaps around the cursor, or the last two at the end.
+    inp.transpose();
+    assert_eq!("foo-".to_string(), inp.input);
+    inp.set_cursor(1);
+    inp.transpose();
+    assert_eq!("ofo-".to_string(), inp.input);
+    assert_eq!(2, inp.cursor);
+    inp.cursor_to_end();
+    inp.transpose();
+    assert_eq!("of-o".to_string(), inp.input);
+    assert_eq!(4, inp.cursor);
+}
+
+#[test]
+fn kill_ring() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let press = |app: &mut App, state: &mut ListState, code, modifiers| {
+        app.handle_key(KeyEvent::new(code, modifiers), state);
+    };
+    app.inputter.insert_str("one two three");
+
+    // Consecutive kills join up, in the order the text was in.
+    press(&mut app, &mut state, KeyCode::Char('w'), KeyModifiers::CONTROL);
+    press(&mut app, &mut state, KeyCode::Char('w'), KeyModifiers::CONTROL);
+    assert_eq!("one ", app.inputter.input);
+    assert_eq!(vec!["two three".to_string()], app.inputter.kill_ring);
+    press(&mut app, &mut state, KeyCode::Char('a'), KeyModifiers::CONTROL);
+    press(&mut app, &mut state, KeyCode::Char('d'), KeyModifiers::ALT);
+    press(&mut app, &mut state, KeyCode::Char('k'), KeyModifiers::CONTROL);
+    assert_eq!("", app.inputter.input);
+    assert_eq!(vec!["two three".to_string(), "one ".to_string()], app.inputter.kill_ring);
+
+    // Yank-pop cycles through older kills, back round to the newest.
+    press(&mut app, &mut state, KeyCode::Char('y'), KeyModifiers::CONTROL | KeyModifiers::ALT);
+    assert_eq!("one ", app.inputter.input);
+    press(&mut app, &mut state, KeyCode::Char('y'), KeyModifiers::ALT);
+    assert_eq!("two three", app.inputter.input);
+    assert_eq!(9, app.inputter.cursor);
+    press(&mut app, &mut state, KeyCode::Char('y'), KeyModifiers::ALT);
+    assert_eq!("one ", app.inputter.input);
+
+    // Only straight after a yank, and kills split by other keys don't join.
+    press(&mut app, &mut state, KeyCode::Char('x'), KeyModifiers::NONE);
+    press(&mut app, &mut state, KeyCode::Char('y'), KeyModifiers::ALT);
+    assert_eq!("one x", app.inputter.input);
+    press(&mut app, &mut state, KeyCode::Char('w'), KeyModifiers::CONTROL);
+    assert_eq!(vec!["two three", "one ", "x"], app.inputter.kill_ring);
+}
+
+#[test]
+fn clipboard() {
+    assert_eq!("", base64(b""));
+    assert_eq!("Zg==", base64(b"f"));
+    assert_eq!("Zm8=", base64(b"fo"));
+    assert_eq!("Zm9v", base64(b"foo"));
+    assert_eq!("Zm9vYmFy", base64(b"foobar"));
+
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    app.inputter.insert_str("hello world");
+
+    // Select "world" with shift+ctrl+left, then cut it.
+    let shift_ctrl = KeyModifiers::SHIFT | KeyModifiers::CONTROL;
+    app.handle_key(key(KeyCode::Left, shift_ctrl), &mut state);
+    assert_eq!(Some("world"), app.inputter.selected_text());
+    app.handle_key(key(KeyCode::Char('x'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!("hello ".to_string(), app.inputter.input);
+    assert_eq!("world".to_string(), app.clipboard.register);
+    assert_eq!(Some("\x1b]52;c;d29ybGQ=\x07".to_string()), app.clipboard.pending.take());
+
+    // Copy with ctrl-c doesn't quit while there's a selection.
+    app.handle_key(key(KeyCode::Left, KeyModifiers::SHIFT), &mut state);
+    app.handle_key(key(KeyCode::Char('c'), KeyModifiers::CONTROL), &mut state);
+    assert!(!app.exit);
+    assert_eq!(" ".to_string(), app.clipboard.register);
+    app.handle_key(key(KeyCode::Home, KeyModifiers::SHIFT), &mut state);
+    assert_eq!(Some("hello "), app.inputter.selected_text());
+    app.handle_key(key(KeyCode::Char('v'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(" ".to_string(), app.inputter.input);
+
+    // A register holding several lines pastes as new todos, not into the input.
+    app.clipboard.register = "milk\neggs\n".into();
+    app.handle_key(key(KeyCode::Char('v'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(" ".to_string(), app.inputter.input);
+    assert_eq!(2, app.pending_paste.take().unwrap().len());
+    app.clipboard.register = "milk\n".into();
+    app.handle_key(key(KeyCode::Char('v'), KeyModifiers::CONTROL), &mut state);
+    assert!(!app.inputter.input.contains('\n'));
+    app.inputter.reset();
+
+    // Yank the selected todo's name from the list.
+    app.new_item("todo".to_string(), &mut state);
+    app.focus = Focus::List;
+    app.handle_key(key(KeyCode::Char('y'), KeyModifiers::NONE), &mut state);
+    assert_eq!("todo".to_string(), app.clipboard.register);
+
+    app.handle_key(key(KeyCode::Char('c'), KeyModifiers::CONTROL), &mut state);
+    assert!(app.exit);
+}
+
+#[test]
+fn input_history() {
+    let path = env::temp_dir().join(format!("todomvc-tui-history-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut history = InputHistory::load(Some(path.clone()));
+    history.add("milk");
+    history.add("eggs");
+    history.add("milk");
+    assert_eq!(vec!["eggs", "milk"], history.entries);
+
+    // Persisted across sessions.
+    let mut history = InputHistory::load(Some(path.clone()));
+    assert_eq!(vec!["eggs", "milk"], history.entries);
+    for i in 0..INPUT_HISTORY_LIMIT {
+        history.push(&i.to_string());
+    }
+    assert_eq!(INPUT_HISTORY_LIMIT, history.entries.len());
+    assert_eq!("0", history.entries[0]);
+    fs::remove_file(&path).unwrap();
+
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    let type_str = |app: &mut App, state: &mut ListState, text: &str| {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c), KeyModifiers::NONE), state);
+        }
+    };
+    for name in ["buy milk", "walk dog", "buy eggs"] {
+        type_str(&mut app, &mut state, name);
+        app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    }
+
+    // Up and down cycle through history, back to the draft.
+    type_str(&mut app, &mut state, "dra");
+    app.handle_key(key(KeyCode::Up, KeyModifiers::NONE), &mut state);
+    assert_eq!("buy eggs".to_string(), app.inputter.input);
+    app.handle_key(key(KeyCode::Up, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Up, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Up, KeyModifiers::NONE), &mut state);
+    assert_eq!("buy milk".to_string(), app.inputter.input);
+    app.handle_key(key(KeyCode::Down, KeyModifiers::NONE), &mut state);
+    assert_eq!("walk dog".to_string(), app.inputter.input);
+    app.handle_key(key(KeyCode::Down, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Down, KeyModifiers::NONE), &mut state);
+    assert_eq!("dra".to_string(), app.inputter.input);
+
+    // Reverse search, then cancel.
+    app.handle_key(key(KeyCode::Char('r'), KeyModifiers::CONTROL), &mut state);
+    type_str(&mut app, &mut state, "buy");
+    assert_eq!("buy eggs".to_string(), app.inputter.input);
+    app.handle_key(key(KeyCode::Char('r'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!("buy milk".to_string(), app.inputter.input);
+    app.handle_key(key(KeyCode::Char('r'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!("buy milk".to_string(), app.inputter.input);
+    type_str(&mut app, &mut state, "x");
+    assert_eq!(Some(("buyx".to_string(), None)), app.input_history.search);
+    app.handle_key(key(KeyCode::Esc, KeyModifiers::NONE), &mut state);
+    assert_eq!("dra".to_string(), app.inputter.input);
+    assert_eq!(Focus::Input, app.focus);
+
+    // Accepting leaves the match in the input.
+    app.handle_key(key(KeyCode::Char('r'), KeyModifiers::CONTROL), &mut state);
+    type_str(&mut app, &mut state, "dog");
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!(None, app.input_history.search);
+    assert_eq!("walk dog".to_string(), app.inputter.input);
+    assert_eq!(3, app.todolist.len());
+}
+
+#[test]
+fn paste() {
+    let names = |ts: &Todos| ts.iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+    let todos = parse_pasted("- [ ] milk\r\n\n* [x] eggs\n  + bread  \n12. jam\n3) tea\n• [X] rice\n-\nplain -text\n", now());
+    assert_eq!(vec!["milk", "eggs", "bread", "jam", "tea", "rice", "plain -text"], names(&todos));
+    assert_eq!(
+        vec![false, true, false, false, false, true, false],
+        todos.iter().map(|t| t.complete).collect::<Vec<_>>()
+    );
+
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+
+    // Single-line pastes go into the input at the cursor, in one step.
+    app.inputter.insert_str("buy ");
+    app.handle_paste("oat milk\n");
+    assert_eq!("buy oat milk".to_string(), app.inputter.input);
+    assert_eq!(12, app.inputter.cursor);
+
+    // Multi-line pastes are previewed, then created as a single undo step.
+    app.handle_paste("a\nb\n\nc");
+    assert_eq!(3, app.pending_paste.as_ref().unwrap().len());
+    app.handle_key(key(KeyCode::Char('x')), &mut state);
+    assert!(app.todolist.is_empty());
+    app.handle_key(key(KeyCode::Esc), &mut state);
+    assert!(app.pending_paste.is_none());
+    assert!(app.todolist.is_empty());
+
+    app.handle_paste("a\nb\n\nc");
+    app.handle_key(key(KeyCode::Enter), &mut state);
+    assert_eq!(vec!["a", "b", "c"], names(&app.todolist));
+    assert_eq!(Some(2), state.selected());
+    assert_eq!("buy oat milk".to_string(), app.inputter.input);
+    app.undo(&mut state);
+    assert!(app.todolist.is_empty());
+}
+
+#[test]
+fn vim() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.vim.enabled = true;
+    let names = |app: &App| app.todolist.iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+    let keys = |app: &mut App, state: &mut ListState, keys: &str| {
+        for c in keys.chars() {
+            let code = match c {
+                '\n' => KeyCode::Enter,
+                '\x1b' => KeyCode::Esc,
+                c => KeyCode::Char(c),
+            };
+            app.handle_key(KeyEvent::new(code, KeyModifiers::NONE), state);
+        }
+    };
+
+    // Starts in insert mode; escape twice to reach the list.
+    keys(&mut app, &mut state, "1\n2\n3\n4\n5\n\x1b");
+    assert_eq!(Mode::Normal, app.vim.mode);
+    assert_eq!(Focus::Input, app.focus);
+    keys(&mut app, &mut state, "\x1b");
+    assert_eq!(Focus::List, app.focus);
+
+    keys(&mut app, &mut state, "gg2j");
+    assert_eq!(Some(2), state.selected());
+    keys(&mut app, &mut state, "2dd");
+    assert_eq!(vec!["1", "2", "5"], names(&app));
+    assert_eq!(Some(2), state.selected());
+    keys(&mut app, &mut state, "kP");
+    assert_eq!(vec!["1", "3", "4", "2", "5"], names(&app));
+    assert_eq!(Some(1), state.selected());
+
+    // Repeat the put, then a deletion with a new count.
+    keys(&mut app, &mut state, "G.");
+    assert_eq!(vec!["1", "3", "4", "2", "3", "4", "5"], names(&app));
+    keys(&mut app, &mut state, "1G3dd.");
+    assert_eq!(vec!["5"], names(&app));
+
+    keys(&mut app, &mut state, "yy2p");
+    assert_eq!(vec!["5", "5", "5"], names(&app));
+    assert_eq!("5".to_string(), app.clipboard.register);
+    keys(&mut app, &mut state, "u");
+    assert_eq!(vec!["5"], names(&app));
+
+    // Open below and above, in insert mode.
+    keys(&mut app, &mut state, "ob\nc\n\x1b\x1bgg");
+    assert_eq!(vec!["5", "b", "c"], names(&app));
+    keys(&mut app, &mut state, "Oa\n\x1b\x1b");
+    assert_eq!(vec!["a", "5", "b", "c"], names(&app));
+    keys(&mut app, &mut state, "2 ");
+    assert!(app.todolist[0].complete && app.todolist[1].complete && !app.todolist[2].complete);
+
+    // Normal and visual mode in the input.
+    app.focus = Focus::Input;
+    keys(&mut app, &mut state, "ione two three\x1b");
+    assert_eq!(Mode::Normal, app.vim.mode);
+    keys(&mut app, &mut state, "0wdw");
+    assert_eq!("one three".to_string(), app.inputter.input);
+    keys(&mut app, &mut state, "0vey");
+    assert_eq!("one".to_string(), app.clipboard.register);
+    assert_eq!(Mode::Normal, app.vim.mode);
+    keys(&mut app, &mut state, "$P");
+    assert_eq!("one threeone".to_string(), app.inputter.input);
+    keys(&mut app, &mut state, "02xiX");
+    assert_eq!("Xe threeone".to_string(), app.inputter.input);
+    keys(&mut app, &mut state, "\x1bbcwY\x1b");
+    assert_eq!("Y threeone".to_string(), app.inputter.input);
+
+    // Keys the config binds take over from vim's.
+    app.keymap = Config::parse("[keys.list]\ndown = \"down\"\nedit = \"j\"").unwrap().keymap;
+    app.focus = Focus::List;
+    keys(&mut app, &mut state, "Gk");
+    assert_eq!(Some(2), state.selected());
+    keys(&mut app, &mut state, "j");
+    assert!(app.editing.is_some());
+    assert_eq!(Some(2), state.selected());
+}
+
+#[test]
+fn quick_add() {
+    // A Wednesday.
+    let now = NaiveDate::from_ymd_opt(2024, 6, 12).unwrap().and_hms_opt(10, 0, 0).unwrap();
+    let date = |m, d| Some(Due { date: NaiveDate::from_ymd_opt(2024, m, d).unwrap(), time: None });
+
+    let add = parse_quick_add("Ship release #work !high due:fri every:week", now);
+    assert_eq!("Ship release", add.name);
+    assert_eq!(vec!["work"], add.tags);
+    assert_eq!(Some(Priority::High), add.priority);
+    assert_eq!(date(6, 14), add.due);
+    assert_eq!(Some(Recurrence { every: 1, unit: Unit::Week }), add.recurrence);
+    let tokens: Vec<_> = add.tokens.iter().map(|(r, t)| (r.start, r.end, *t)).collect();
+    assert_eq!(vec![
+        (13, 18, Token::Tag),
+        (19, 24, Token::Priority),
+        (25, 32, Token::Due),
+        (33, 43, Token::Recurrence),
+    ], tokens);
+
+    // Metadata anywhere, repeated tags once, weekdays including today.
+    let add = parse_quick_add("#a call  #b mum #a due:wed !L", now);
+    assert_eq!("call mum", add.name);
+    assert_eq!(vec!["a", "b"], add.tags);
+    assert_eq!(Some(Priority::Low), add.priority);
+    assert_eq!(date(6, 12), add.due);
+    assert_eq!(date(6, 13), parse_quick_add("x due:tomorrow", now).due);
+    assert_eq!(date(7, 1), parse_quick_add("x due:2024-07-01", now).due);
+    assert_eq!(date(6, 12), parse_quick_add("x due:\"today\"", now).due);
+    assert_eq!(
+        Some(Recurrence { every: 2, unit: Unit::Month }),
+        parse_quick_add("x every:2months", now).recurrence
+    );
+
+    // Not metadata, or not understood, stays in the name.
+    let add = parse_quick_add("fix #123 now! due:someday every:2daily # !", now);
+    assert_eq!("fix #123 now! due:someday every:2daily # !", add.name);
+    assert_eq!(None, add.due);
+    assert_eq!(vec![Token::Invalid, Token::Invalid], add.tokens.iter().map(|(_, t)| *t).collect::<Vec<_>>());
+
+    // Escaping, and quoted values keeping their spaces.
+    let add = parse_quick_add(r"use \#hashtags \\#x #y", now);
+    assert_eq!(r"use #hashtags \#x", add.name);
+    assert_eq!(vec!["y"], add.tags);
+    assert_eq!(vec![(4, 5), (15, 16)], add.tokens[..2].iter().map(|(r, _)| (r.start, r.end)).collect::<Vec<_>>());
+    let add = parse_quick_add("a due:\"next fri 9am\" b", now);
+    assert_eq!(("a b", Some(NaiveTime::from_hms_opt(9, 0, 0).unwrap())), (add.name.as_str(), add.due.unwrap().time));
+    let add = parse_quick_add("a due:\"no such day\" b", now);
+    assert_eq!("a due:\"no such day\" b", add.name);
+    assert_eq!(r"\#a b \!high \\c due:x", escape_quick_add(r"#a b !high \c due:x", now));
+
+    // Dates read on over as many words as make sense.
+    let add = parse_quick_add("call due:next monday 9am about #work", now);
+    assert_eq!(("call about", vec!["work".to_string()]), (add.name.as_str(), add.tags));
+    assert_eq!(Some(Due {
+        date: NaiveDate::from_ymd_opt(2024, 6, 17).unwrap(),
+        time: NaiveTime::from_hms_opt(9, 0, 0),
+    }), add.due);
+    assert_eq!((5, 24), (add.tokens[0].0.start, add.tokens[0].0.end));
+    assert_eq!("2 things", parse_quick_add("due:may 5 2 things", now).name);
+}
+
+#[test]
+fn quick_add_todos() {
+    let now = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap().and_hms_opt(10, 0, 0).unwrap();
+    let mut t = Todo::from_quick_add("pay rent due:today 9am every:month", now);
+    t.complete(now);
+    assert!(!t.complete);
+    assert_eq!("2024-02-29T09:00", t.due.unwrap().iso());
+    t.toggle();
+    assert_eq!("2024-03-29T09:00", t.due.unwrap().iso());
+
+    // Editing shows the metadata, and saving replaces it.
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.new_item(r"\#a fan #home !m due:2024-02-03 every:2weeks".to_string(), &mut state);
+    app.new_item("#only #tags".to_string(), &mut state);
+    assert_eq!(1, app.todolist.len());
+    let t = &app.todolist[0];
+    assert_eq!("#a fan", t.name);
+    assert_eq!(r"\#a fan #home !medium due:2024-02-03 every:2weeks", t.quick_add(crate::now()));
+    let input = t.quick_add(crate::now());
+    app.begin_editing(&state);
+    assert_eq!(input, app.inputter.input);
+    app.finish_editing(r"\#a fans #work".to_string(), 0, &state);
+    let t = &app.todolist[0];
+    assert_eq!(("#a fans", vec!["work".to_string()], None, None), (t.name.as_str(), t.tags.clone(), t.priority, t.due));
+
+    // Editing and saving without changes keeps the todo as it was, even
+    // with a literal `due:` that would read on into the next words.
+    for input in [r"meet \due:next week", r"a \due:feb 29 b due:mar 1", r"\\x \#y \every:day #z"] {
+        let before = Todo::from_quick_add(input, now);
+        assert_eq!(before, Todo::from_quick_add(&before.quick_add(now), now), "{input}");
+    }
+    assert_eq!(r"meet \due:next week", Todo::new("meet due:next week".to_string()).quick_add(now));
+    let mut app = App::new();
+    app.new_item(r"meet \due:next week".to_string(), &mut state);
+    app.focus = Focus::List;
+    app.handle_key(KeyEvent::new(KeyCode::Char('e'), KeyModifiers::NONE), &mut state);
+    assert_eq!(r"meet \due:next week", app.inputter.input);
+    app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    let t = &app.todolist[0];
+    assert_eq!(("meet due:next week", None), (t.name.as_str(), t.due));
+}
+
+#[test]
+fn dates() {
+    // A Wednesday, in the morning.
+    let now = NaiveDate::from_ymd_opt(2024, 6, 12).unwrap().and_hms_opt(10, 30, 0).unwrap();
+    let parse = |text| parse_date(text, now).map(Due::iso);
+    let cases = [
+        ("today", "2024-06-12"),
+        ("Tomorrow", "2024-06-13"),
+        ("yesterday", "2024-06-11"),
+        ("tomorrow 9am", "2024-06-13T09:00"),
+        ("tomorrow at 9:15 PM", "2024-06-13T21:15"),
+        ("9am tomorrow", "2024-06-13T09:00"),
+        ("tom noon", "2024-06-13T12:00"),
+        ("wed", "2024-06-12"),
+        ("this friday", "2024-06-14"),
+        ("monday", "2024-06-17"),
+        ("next monday", "2024-06-17"),
+        ("next wednesday", "2024-06-19"),
+        ("next week", "2024-06-19"),
+        ("next month", "2024-07-12"),
+        ("next year", "2025-06-12"),
+        ("in 3 days", "2024-06-15"),
+        ("in a week", "2024-06-19"),
+        ("in 2 months", "2024-08-12"),
+        ("in 1 year", "2025-06-12"),
+        ("in 2 hours", "2024-06-12T12:30"),
+        ("in 90 minutes", "2024-06-12T12:00"),
+        ("in 14 hours", "2024-06-13T00:30"),
+        ("now", "2024-06-12T10:30"),
+        ("end of week", "2024-06-16"),
+        ("end of month", "2024-06-30"),
+        ("eom 5pm", "2024-06-30T17:00"),
+        ("end of year", "2024-12-31"),
+        ("2024-06-30", "2024-06-30"),
+        ("2024-06-30T09:05", "2024-06-30T09:05"),
+        ("2024-06-30 21:00", "2024-06-30T21:00"),
+        ("jun 30", "2024-06-30"),
+        ("1st july", "2024-07-01"),
+        ("March 3rd", "2025-03-03"),
+        ("dec 25, 2030", "2030-12-25"),
+        // A time alone is the next one to come.
+        ("11am", "2024-06-12T11:00"),
+        ("9 am", "2024-06-13T09:00"),
+        ("midnight", "2024-06-13T00:00"),
+        ("17:45", "2024-06-12T17:45"),
+    ];
+    for (text, iso) in cases {
+        assert_eq!(Some(iso.to_string()), parse(text), "{text}");
+    }
+    for text in [
+        "", "someday", "13pm", "0am", "25:00", "9:5", "in 3 parsecs", "in 2 hours 9am",
+        "feb 30", "2024-13-01", "at", "next", "30", "tomorrow tomorrow", "at home",
+    ] {
+        assert_eq!(None, parse(text), "{text}");
+    }
+
+    // Round trips, and relative formatting.
+    let due = parse_date("tomorrow 9am", now).unwrap();
+    assert_eq!(Some(due), parse_date(&due.iso(), now));
+    assert_eq!("tomorrow 09:00", due.fmt(now.date()));
+    assert_eq!("Sun 30 Jun", parse_date("eom", now).unwrap().fmt(now.date()));
+    assert_eq!("Mon 3 Mar 2025", parse_date("mar 3", now).unwrap().fmt(now.date()));
+    assert!(parse_date("today 10am", now).unwrap().is_past(now));
+    assert!(!parse_date("today", now).unwrap().is_past(now));
+}
+
+#[test]
+fn completion() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    let type_str = |app: &mut App, state: &mut ListState, text: &str| {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c), KeyModifiers::NONE), state);
+        }
+    };
+    app.new_item("a #work #home".to_string(), &mut state);
+    app.new_item("b #Wishlist #work".to_string(), &mut state);
+
+    // Tags from todos, case-insensitively by prefix.
+    type_str(&mut app, &mut state, "Ship #w");
+    assert_eq!(vec!["Wishlist", "work"], app.completion.items);
+    assert_eq!(6, app.completion.start);
+    app.handle_key(key(KeyCode::Tab, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Tab, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Tab, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(0), app.completion.selected);
+    app.handle_key(key(KeyCode::BackTab, KeyModifiers::SHIFT), &mut state);
+    assert_eq!(Focus::Input, app.focus);
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!("Ship #work ".to_string(), app.inputter.input);
+    assert!(app.completion.items.is_empty());
+    assert_eq!(2, app.todolist.len());
+
+    // Date keywords, and dismissing until the word changes.
+    type_str(&mut app, &mut state, "due:");
+    assert_eq!(DATE_KEYWORDS.len(), app.completion.items.len());
+    type_str(&mut app, &mut state, "ne");
+    assert_eq!(vec!["next week", "next month", "next year"], app.completion.items);
+    app.handle_key(key(KeyCode::Esc, KeyModifiers::NONE), &mut state);
+    assert_eq!(Focus::Input, app.focus);
+    assert!(app.completion.items.is_empty());
+    app.handle_key(key(KeyCode::Char('a'), KeyModifiers::CONTROL), &mut state);
+    assert!(app.completion.items.is_empty());
+    app.handle_key(key(KeyCode::End, KeyModifiers::NONE), &mut state);
+    assert_eq!(3, app.completion.items.len());
+    type_str(&mut app, &mut state, "xt m");
+    assert!(app.completion.items.is_empty());
+    app.handle_key(key(KeyCode::Backspace, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Backspace, KeyModifiers::NONE), &mut state);
+    assert_eq!(3, app.completion.items.len());
+
+    // Enter without a selection still saves, and tab still moves on.
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!(3, app.todolist.len());
+    assert_eq!(vec!["work"], app.todolist[2].tags);
+    app.handle_key(key(KeyCode::Tab, KeyModifiers::NONE), &mut state);
+    assert_eq!(Focus::Buttons, app.focus);
+
+    // List names complete as tags too, unless they have spaces.
+    app.lists.push(("weekend".to_string(), Vec::new()));
+    app.lists.push(("Work trips".to_string(), Vec::new()));
+    app.focus = Focus::Input;
+    type_str(&mut app, &mut state, "#w");
+    assert_eq!(vec!["Wishlist", "work", "weekend"], app.completion.items);
+    type_str(&mut app, &mut state, " #to");
+    assert_eq!(vec!["Todos"], app.completion.items);
+}
+
+#[test]
+fn suggestions() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    for name in ["buy milk", "buy eggs", "bake bread"] {
+        app.new_item(name.to_string(), &mut state);
+    }
+    app.todolist[0].complete = true;
+    app.todolist[2].complete = true;
+
+    app.inputter.insert_str("b");
+    assert_eq!(Some("ake bread"), app.suggestion());
+    app.inputter.insert_str("uy");
+    assert_eq!(Some(" milk"), app.suggestion());
+    app.inputter.left();
+    assert_eq!(None, app.suggestion());
+    app.handle_key(key(KeyCode::Right, KeyModifiers::NONE), &mut state);
+    assert_eq!("buy".to_string(), app.inputter.input);
+    app.handle_key(key(KeyCode::Right, KeyModifiers::NONE), &mut state);
+    assert_eq!("buy milk".to_string(), app.inputter.input);
+    assert_eq!(None, app.suggestion());
+
+    // Only from completed todos.
+    app.inputter.reset();
+    app.inputter.insert_str("buy e");
+    assert_eq!(None, app.suggestion());
+    app.handle_key(key(KeyCode::Char('e'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!("buy e".to_string(), app.inputter.input);
+}
+
+#[test]
+fn fuzzy() {
+    assert_eq!(Some((0, vec![])), fuzzy_match("", "anything"));
+    assert_eq!(None, fuzzy_match("xyz", "buy milk"));
+    assert_eq!(None, fuzzy_match("Milk", "buy milk"));
+    assert_eq!(vec![0, 4], fuzzy_match("bm", "buy milk").unwrap().1);
+    assert_eq!(vec![4, 5, 6], fuzzy_match("MIL", "buy MILk").unwrap().1);
+    // Word starts and consecutive runs win over earlier scattered matches.
+    assert_eq!(vec![6, 7], fuzzy_match("ca", "clear cat").unwrap().1);
+    let score = |q, t| fuzzy_match(q, t).unwrap().0;
+    assert!(score("milk", "buy milk") > score("milk", "make it look"));
+    assert!(score("bm", "buy milk") > score("bm", "submit"));
+}
+
+#[test]
+fn search() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    let type_str = |app: &mut App, state: &mut ListState, text: &str| {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c)), state);
+        }
+    };
+    for name in ["buy milk", "walk dog", "bake #bread", "make lunch", "call mum"] {
+        app.new_item(name.to_string(), &mut state);
+    }
+    app.focus = Focus::List;
+    state.select(Some(1));
+    *state.offset_mut() = 1;
+
+    // Typing filters to matches and selects the best.
+    type_str(&mut app, &mut state, "/m");
+    assert!(app.search.as_ref().unwrap().typing);
+    assert_eq!(vec![0, 3, 4], app.search_matches("m").iter().map(|(i, ..)| *i).collect::<Vec<_>>());
+    type_str(&mut app, &mut state, "k");
+    assert_eq!(Some(3), state.selected());
+    app.handle_key(key(KeyCode::Up), &mut state);
+    assert_eq!(Some(0), state.selected());
+    app.handle_key(key(KeyCode::Up), &mut state);
+    assert_eq!(Some(0), state.selected());
+    type_str(&mut app, &mut state, "zz");
+    assert_eq!(None, state.selected());
+    app.handle_key(key(KeyCode::Backspace), &mut state);
+    app.handle_key(key(KeyCode::Backspace), &mut state);
+
+    // Escape restores the selection and scroll exactly.
+    app.handle_key(key(KeyCode::Esc), &mut state);
+    assert!(app.search.is_none());
+    assert_eq!((Some(1), 1), (state.selected(), state.offset()));
+
+    // Tags match too; enter keeps the selection and n/N jump around.
+    type_str(&mut app, &mut state, "/#b");
+    assert_eq!(Some(2), state.selected());
+    assert_eq!(vec![(2, vec![5, 6])], app.search_matches("#b").into_iter().map(|(i, _, m)| (i, m)).collect::<Vec<_>>());
+    for _ in 0..3 {
+        app.handle_key(key(KeyCode::Backspace), &mut state);
+    }
+    assert!(app.search.is_none());
+    type_str(&mut app, &mut state, "/ak");
+    app.handle_key(key(KeyCode::Enter), &mut state);
+    assert_eq!(Some(2), state.selected());
+    assert!(!app.search.as_ref().unwrap().typing);
+    type_str(&mut app, &mut state, "n");
+    assert_eq!(Some(3), state.selected());
+    type_str(&mut app, &mut state, "n");
+    assert_eq!(Some(1), state.selected());
+    type_str(&mut app, &mut state, "NN");
+    assert_eq!(Some(2), state.selected());
+    app.handle_key(key(KeyCode::Esc), &mut state);
+    assert!(app.search.is_none());
+    assert_eq!(Some(2), state.selected());
+}
+
+#[test]
+fn sorting() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    let day = |d| NaiveDate::from_ymd_opt(2024, 6, d).unwrap();
+    for name in ["zoo !low", "École due:2024-06-20", "ecole !high", "apple due:2024-06-10 !low", "Banana"] {
+        app.new_item(name.to_string(), &mut state);
+    }
+    app.todolist[1].completed_at = Some(day(1).and_hms_opt(9, 0, 0).unwrap());
+    app.todolist[1].complete = true;
+    app.todolist[4].completed_at = Some(day(2).and_hms_opt(9, 0, 0).unwrap());
+    app.todolist[4].complete = true;
+
+    // Accents and case are ignored and ties keep insertion order.
+    assert_eq!(vec![3, 4, 1, 2, 0], Sort::Alphabetical.order(&app.todolist, &app.collator));
+    assert_eq!(vec![0, 1, 2, 3, 4], Sort::Insertion.order(&app.todolist, &app.collator));
+    assert_eq!(vec![0, 2, 3, 1, 4], Sort::ActiveFirst.order(&app.todolist, &app.collator));
+    assert_eq!(vec![4, 1, 0, 2, 3], Sort::RecentlyCompleted.order(&app.todolist, &app.collator));
+    assert_eq!(vec![3, 1, 0, 2, 4], Sort::DueDate.order(&app.todolist, &app.collator));
+    assert_eq!(vec![2, 0, 3, 1, 4], Sort::Priority.order(&app.todolist, &app.collator));
+
+    // Sorting follows the locale's alphabet, and unknown locales the root's.
+    assert_eq!(Ordering::Less, collator("").compare("öl", "zoo"));
+    assert_eq!(Ordering::Greater, collator("sv_SE.UTF-8").compare("öl", "zoo"));
+    assert_eq!(Ordering::Greater, collator("de_DE").compare("Straßen", "Strasse"));
+    assert_eq!(Ordering::Equal, collator("de_DE@euro").compare("Straße", "STRASSE"));
+    assert_eq!(Ordering::Less, collator("C").compare("Éa", "eb"));
+
+    // Changing the sort keeps the selection, and navigation follows it.
+    app.focus = Focus::List;
+    state.select(Some(2));
+    app.handle_key(key(KeyCode::Char('s')), &mut state);
+    assert_eq!(Some(0), app.sort_menu);
+    app.handle_key(key(KeyCode::Down), &mut state);
+    app.handle_key(key(KeyCode::Enter), &mut state);
+    assert_eq!((Sort::Alphabetical, None), (app.sort, app.sort_menu));
+    assert_eq!(Some(2), state.selected());
+    app.handle_key(key(KeyCode::Down), &mut state);
+    assert_eq!(Some(0), state.selected());
+    app.handle_key(key(KeyCode::Up), &mut state);
+    app.handle_key(key(KeyCode::Up), &mut state);
+    assert_eq!(Some(1), state.selected());
+
+    // Deleting selects the previous item shown.
+    app.handle_key(key(KeyCode::Backspace), &mut state);
+    assert_eq!(vec!["zoo", "ecole", "apple", "Banana"], app.todolist.iter().map(|t| t.name.as_str()).collect::<Vec<_>>());
+    assert_eq!(Some(3), state.selected());
+
+    // Digits choose directly, escape leaves the sort alone.
+    app.handle_key(key(KeyCode::Char('s')), &mut state);
+    app.handle_key(key(KeyCode::Char('6')), &mut state);
+    assert_eq!(Sort::Priority, app.sort);
+    app.handle_key(key(KeyCode::Char('s')), &mut state);
+    app.handle_key(key(KeyCode::Char('j')), &mut state);
+    app.handle_key(key(KeyCode::Esc), &mut state);
+    assert_eq!((Sort::Priority, None), (app.sort, app.sort_menu));
+
+    // Modes are persisted per list.
+    let path = env::temp_dir().join(format!("todomvc-tui-sort-{}", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut modes = SortModes::load(Some(path.clone()));
+    assert_eq!(Sort::Insertion, modes.get("work"));
+    modes.set("work", Sort::DueDate);
+    modes.set("home", Sort::ActiveFirst);
+    modes.set("work", Sort::RecentlyCompleted);
+    let modes = SortModes::load(Some(path.clone()));
+    assert_eq!((Sort::RecentlyCompleted, Sort::ActiveFirst), (modes.get("work"), modes.get("home")));
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn bulk() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    let type_str = |app: &mut App, state: &mut ListState, text: &str| {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c), KeyModifiers::NONE), state);
+        }
+    };
+    let names = |app: &App| app.todolist.iter().map(|t| t.name.clone()).collect::<Vec<_>>();
+    for name in ["a", "b", "c", "d", "e"] {
+        app.new_item(name.to_string(), &mut state);
+    }
+    app.focus = Focus::List;
+
+    // Marks by toggling and by shift ranges.
+    state.select(Some(0));
+    type_str(&mut app, &mut state, "x");
+    app.handle_key(key(KeyCode::Down, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Down, KeyModifiers::SHIFT), &mut state);
+    app.handle_key(key(KeyCode::Down, KeyModifiers::SHIFT), &mut state);
+    assert_eq!(vec![0, 1, 2, 3], app.targets(&state));
+    type_str(&mut app, &mut state, "x");
+    assert_eq!(vec![0, 1, 2], app.targets(&state));
+
+    // Toggling completes them all, then makes them all active again.
+    app.todolist[1].complete = true;
+    app.handle_key(key(KeyCode::Char(' '), KeyModifiers::NONE), &mut state);
+    assert!(app.todolist[..3].iter().all(|t| t.complete));
+    assert!(!app.todolist[3].complete);
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert!(app.todolist.iter().all(|t| !t.complete));
+
+    // Tags and priority are set through the input, and clear the marks.
+    type_str(&mut app, &mut state, "#");
+    assert_eq!(Focus::Input, app.focus);
+    assert_eq!(Some(Prompt::Tag), app.prompt);
+    type_str(&mut app, &mut state, "work #home");
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!(Focus::List, app.focus);
+    assert_eq!(None, app.prompt);
+    assert_eq!(vec!["work", "home"], app.todolist[2].tags);
+    assert!(app.todolist[3].tags.is_empty());
+    assert_eq!(vec![3], app.targets(&state));
+    type_str(&mut app, &mut state, "!bogus");
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(Prompt::Priority), app.prompt);
+    app.handle_key(key(KeyCode::Esc, KeyModifiers::NONE), &mut state);
+    assert_eq!(None, app.prompt);
+    assert!(app.inputter.input.is_empty());
+    app.focus = Focus::List;
+    type_str(&mut app, &mut state, "!h");
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(Priority::High), app.todolist[3].priority);
+
+    // Marked todos are deleted together, keeping the selection, and undone.
+    state.select(Some(3));
+    for i in [0, 2] {
+        app.todolist[i].marked = true;
+    }
+    app.handle_key(key(KeyCode::Backspace, KeyModifiers::NONE), &mut state);
+    assert_eq!(vec!["b", "d", "e"], names(&app));
+    assert_eq!(Some(1), state.selected());
+    app.undo(&mut state);
+    assert_eq!(5, app.todolist.len());
+
+    // Moving makes a new list, and lists can be switched between.
+    app.handle_key(key(KeyCode::Esc, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Up, KeyModifiers::SHIFT), &mut state);
+    type_str(&mut app, &mut state, ">Work");
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!(vec!["a", "b", "e"], names(&app));
+    assert_eq!(vec![("Work".to_string(), 2)], app.lists.iter().map(|(n, t)| (n.clone(), t.len())).collect::<Vec<_>>());
+    type_str(&mut app, &mut state, "LWork");
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!("Work", app.list);
+    assert_eq!(vec!["c", "d"], names(&app));
+    assert!(app.todolist.iter().all(|t| !t.marked));
+    assert_eq!(Some(0), state.selected());
+
+    // Undo goes back to the list the change was made in.
+    app.undo(&mut state);
+    assert_eq!("Todos", app.list);
+    assert_eq!(vec!["a", "b", "c", "d", "e"], names(&app));
+    assert!(app.lists.is_empty());
+
+    // List prompts complete the names of the other lists.
+    type_str(&mut app, &mut state, "LHome");
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!("Home", app.list);
+    type_str(&mut app, &mut state, "Lt");
+    assert_eq!(vec!["Todos"], app.completion.items);
+    app.handle_key(key(KeyCode::Tab, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!("Todos ", app.inputter.input);
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert_eq!(("Todos", 5), (app.list.as_str(), app.todolist.len()));
+    type_str(&mut app, &mut state, ">#");
+    assert!(app.completion.items.is_empty());
+}
+
+#[test]
+fn palette() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+    let type_str = |app: &mut App, state: &mut ListState, text: &str| {
+        for c in text.chars() {
+            app.handle_key(key(KeyCode::Char(c), KeyModifiers::NONE), state);
+        }
+    };
+    for name in ["walk dog #pets", "buy milk"] {
+        app.new_item(name.to_string(), &mut state);
+    }
+    app.todolist[1].toggle();
+
+    // Every action has a name and the keys shown are the ones bound.
+    let keymap = Keymap::default();
+    assert_eq!(Some(Action::PreviousMatch), keymap.get(Context::List, key(KeyCode::Char('N'), KeyModifiers::SHIFT)));
+    assert_eq!(None, keymap.get(Context::List, key(KeyCode::Char('e'), KeyModifiers::ALT)));
+    assert_eq!(Some("space".to_string()), keymap.key(Action::Toggle));
+    assert_eq!(Some("ctrl-r".to_string()), keymap.key(Action::Redo));
+    for action in ACTIONS {
+        assert!(keymap.key(action).is_none_or(|k| !k.is_empty()), "{action:?}");
+    }
+
+    // Fuzzy search picks the action, even from the input.
+    app.handle_key(key(KeyCode::Char('p'), KeyModifiers::CONTROL), &mut state);
+    type_str(&mut app, &mut state, "clrcomp");
+    assert_eq!(Action::ClearCompleted, app.palette.as_ref().unwrap().matches()[0].0);
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert!(app.palette.is_none());
+    assert_eq!(1, app.todolist.len());
+    assert!(app.inputter.input.is_empty());
+
+    // Moving around the matches, and escape closes without acting.
+    app.handle_key(key(KeyCode::Char('p'), KeyModifiers::CONTROL), &mut state);
+    type_str(&mut app, &mut state, "xyzzy");
+    assert!(app.palette.as_ref().unwrap().matches().is_empty());
+    app.handle_key(key(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Char('p'), KeyModifiers::CONTROL), &mut state);
+    app.handle_key(key(KeyCode::Down, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Char('n'), KeyModifiers::CONTROL), &mut state);
+    app.handle_key(key(KeyCode::Char('p'), KeyModifiers::CONTROL), &mut state);
+    assert_eq!(1, app.palette.as_ref().unwrap().selected);
+    app.handle_key(key(KeyCode::Esc, KeyModifiers::NONE), &mut state);
+    assert!(app.palette.is_none());
+    assert_eq!(1, app.todolist.len());
+
+    // Export copies the list as markdown that pastes back in.
+    app.todolist[0].toggle();
+    app.perform(Action::Export, &mut state);
+    assert_eq!("- [x] walk dog #pets", app.clipboard.register);
+    assert!(parse_pasted(&app.clipboard.register, now())[0].complete);
+    let mut exported = App::new();
+    for name in [r"Ship \#1 release #work !high due:2030-01-02 every:week", r"say \\hi", "done #x"] {
+        exported.new_item(name.to_string(), &mut state);
+    }
+    exported.todolist[2].toggle();
+    exported.perform(Action::Export, &mut state);
+    let pasted = parse_pasted(&exported.clipboard.register, now());
+    for (before, after) in exported.todolist.iter().zip(&pasted) {
+        assert_eq!(Todo { completed_at: after.completed_at, ..before.clone() }, *after);
+    }
+
+    // Keys in the list go through the same actions.
+    app.focus = Focus::List;
+    type_str(&mut app, &mut state, "u");
+    assert!(!app.todolist[0].complete);
+    app.handle_key(key(KeyCode::Char('c'), KeyModifiers::CONTROL), &mut state);
+    assert!(app.exit);
+}
+
+#[test]
+fn keymap() {
+    let key = |code, modifiers| KeyEvent::new(code, modifiers);
+
+    // No key is bound twice in a context, and commands all have keys.
+    for (i, (context, modifiers, code, _)) in KEYMAP.iter().enumerate() {
+        assert!(!KEYMAP[..i].iter().any(|(c, m, k, _)| c == context && m == modifiers && k == code), "{context:?} {code:?}");
+    }
+    let keymap = Keymap::default();
+    assert!(keymap.conflicts().is_empty());
+    for action in ACTIONS {
+        assert!(keymap.key(action).is_some(), "{action:?}");
+    }
+    assert_eq!(vec!["space", "enter"], keymap.keys(Action::Toggle, Context::List));
+    assert_eq!(vec!["shift-tab"], keymap.keys(Action::FocusPrevious, Context::Global));
+    assert_eq!(Some(Action::Yank), keymap.get(Context::Input, key(KeyCode::Char('y'), KeyModifiers::CONTROL | KeyModifiers::ALT)));
+    assert_eq!(None, keymap.get(Context::Input, key(KeyCode::Char('y'), KeyModifiers::CONTROL)));
+    assert_eq!("ctrl-shift-left", key_name((KeyModifiers::SHIFT | KeyModifiers::CONTROL, KeyCode::Left)));
+
+    // The footer follows focus and is cut short on narrow terminals.
+    let text = |line: Line| line.spans.iter().map(|s| s.content.to_string()).collect::<String>();
+    assert_eq!(
+        "ctrl-c: quit, enter: save, esc: cancel, tab: switch focus, ctrl-p: commands",
+        text(keymap.footer(Context::Input, 100, Style::default())),
+    );
+    assert!(text(keymap.footer(Context::List, 200, Style::default())).contains("space/enter: toggle complete"));
+    assert_eq!("ctrl-c: quit, space/enter: toggle complete", text(keymap.footer(Context::List, 50, Style::default())));
+    assert_eq!("", text(keymap.footer(Context::List, 5, Style::default())));
+
+    // Focus moves through the table, and ? opens the overlay outside the input.
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.handle_key(key(KeyCode::BackTab, KeyModifiers::SHIFT), &mut state);
+    assert_eq!(Focus::List, app.focus);
+    app.handle_key(key(KeyCode::Char('?'), KeyModifiers::SHIFT), &mut state);
+    assert_eq!(Some(0), app.help);
+    app.handle_key(key(KeyCode::Char('j'), KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(1), app.help);
+    app.handle_key(key(KeyCode::Esc, KeyModifiers::NONE), &mut state);
+    assert_eq!(None, app.help);
+    app.handle_key(key(KeyCode::Tab, KeyModifiers::NONE), &mut state);
+    app.handle_key(key(KeyCode::Char('?'), KeyModifiers::SHIFT), &mut state);
+    assert_eq!("?", app.inputter.input);
+    assert_eq!(None, app.help);
+
+    // The overlay notes why space doesn't mark.
+    let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(80, 200)).unwrap();
+    app.help = Some(0);
+    terminal.draw(|frame| app.draw_help(frame)).unwrap();
+    let buffer = terminal.backend().buffer();
+    let text: Vec<String> = (0..200).map(|y| (0..80).map(|x| buffer.get(x, y).symbol()).collect()).collect();
+    assert!(text.iter().any(|l| l.contains("ctrl-y  Redo") && !l.contains("except")));
+    assert!(text.iter().any(|l| l.contains("Space toggles completion as in TodoMVC, so x marks todos.")));
+}
+
+#[test]
+fn config() {
+    let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+
+    // Keys parse back from the names shown for them.
+    for (modifiers, code, _) in KEYMAP.iter().map(|&(_, m, k, a)| (m, k, a)) {
+        assert_eq!(Some((modifiers, code)), parse_chord(&key_name((modifiers, code))), "{code:?}");
+    }
+    assert_eq!(Some((KeyModifiers::NONE, KeyCode::Char('G'))), parse_chord("shift-g"));
+    assert_eq!(Some((KeyModifiers::NONE, KeyCode::F(2))), parse_chord("f2"));
+    assert_eq!(None, parse_chord("hyper-x"));
+    assert_eq!("mark-all", Action::MarkAll.id());
+
+    // Remapping frees the vim keys, and sequences wait for their last key.
+    let config = Config::parse(
+        r#"
+        [keys.global]
+        palette = "ctrl-o"
+        [keys.list]
+        down = "down"
+        up = ["up", "ctrl-p"]
+        edit = "f2"
+        mark = []
+        clear-completed = "g c"
+        first = "g g"
+        "#,
+    )
+    .unwrap();
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.keymap = config.keymap;
+    for name in ["walk dog", "buy milk"] {
+        app.new_item(name.to_string(), &mut state);
+    }
+    app.focus = Focus::List;
+    state.select(Some(1));
+    for c in "jkem".chars() {
+        app.handle_key(key(c), &mut state);
+    }
+    assert_eq!(Some(1), state.selected());
+    assert!(app.editing.is_none() && app.todolist.iter().all(|t| !t.marked));
+    assert_eq!(vec!["up", "ctrl-p"], app.keymap.keys(Action::Up, Context::List));
+    app.handle_key(key('g'), &mut state);
+    assert_eq!(Some(1), state.selected());
+    app.handle_key(key('g'), &mut state);
+    assert_eq!(Some(0), state.selected());
+    app.handle_key(KeyEvent::new(KeyCode::Char('p'), KeyModifiers::CONTROL), &mut state);
+    assert!(app.palette.is_none());
+
+    // A sequence cut short retries the last key on its own.
+    app.handle_key(key('g'), &mut state);
+    app.handle_key(key('G'), &mut state);
+    assert_eq!(Some(1), state.selected());
+    assert!(app.pending_keys.is_empty());
+
+    // Every problem is reported at once, in the order of the tables.
+    let errors = Config::parse(
+        r#"
+        colour = "dark"
+        [keys.list]
+        frobnicate = "f"
+        first = ["g", 1]
+        last = "g g"
+        down = "hyper-j"
+        toggle = "x"
+        [keys.lists]
+        [keys.input]
+        yank = "ctrl-y"
+        [keys.board]
+        group-board = "e"
+        "#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        vec![
+            "unknown setting \"colour\"",
+            "[keys.list]: down: unknown key \"hyper-j\"",
+            "[keys.list]: first: expected a key or a list of keys",
+            "[keys.list]: unknown action \"frobnicate\"",
+            "unknown section [keys.lists], expected one of: global, input, buttons, list, board",
+            "[keys.board]: \"e\" for group-board hides \"e\" for edit in [keys.list]",
+            "[keys.input]: \"ctrl-y\" for yank hides \"ctrl-y\" for redo in [keys.global]",
+            "[keys.list]: \"g\" for first starts \"g g\" for last",
+            "[keys.list]: \"x\" is bound to both mark and toggle",
+        ],
+        errors,
+    );
+    assert_eq!(1, Config::parse("[keys.list").unwrap_err().len());
+    assert!(Config::load(None).is_ok());
+
+    // Syntax errors give the line and column, and unreadable files are
+    // reported rather than skipped.
+    let errors = Config::parse("theme = \"dusk\"\n[keys.list]\ndown = \"j").unwrap_err();
+    assert!(errors[0].starts_with("TOML parse error at line 3, column 10"), "{}", errors[0]);
+    let path = env::temp_dir().join(format!("todomvc-tui-config-{}", std::process::id()));
+    assert!(Config::load(Some(path.clone())).is_ok());
+    fs::write(&path, b"theme = \"\xff\"").unwrap();
+    assert_eq!(1, Config::load(Some(path.clone())).unwrap_err().len());
+    fs::remove_file(&path).unwrap();
+    assert!(Config::load(Some(env::temp_dir())).is_err());
+}
+
+#[test]
+fn themes() {
+    let text = |t: Text| t.lines[1].spans.iter().map(|s| s.content.to_string()).collect::<String>();
+    let now = now();
+
+    // Styles are words, with the background after "on".
+    assert_eq!(Some(Style::new().yellow().bold()), parse_style("bold yellow"));
+    assert_eq!(Some(Style::new().white().bg(Color::Rgb(65, 70, 80))), parse_style("white on #414650"));
+    assert_eq!(Some(Style::new().fg(Color::Indexed(208)).underlined()), parse_style("underlined 208"));
+    assert_eq!(None, parse_style("blinking"));
+    assert_eq!(None, parse_style("white on"));
+
+    // Themes change the glyphs of todos along with their styles.
+    let mut todo = Todo::from_quick_add("walk dog #pets !high", now);
+    let default = Theme::default();
+    assert_eq!("   ( ) walk dog #pets !high", text(todo.fmt_item(now, &[], &default)));
+    assert_eq!(default.high, todo.fmt_item(now, &[], &default).lines[1].spans[4].style);
+    assert_eq!(Style::new().cyan().patch(default.matched), todo.fmt_item(now, &[9], &default).lines[1].spans[4].style);
+    let classic = Theme::builtin().into_iter().find(|t| t.name == "classic").unwrap();
+    todo.toggle();
+    todo.marked = true;
+    assert_eq!(" > [x] walk dog #pets !high", text(todo.fmt_item(now, &[], &classic)));
+    assert_eq!(3..6, default.checkbox_columns());
+
+    // The config picks a theme, and can make new ones from the built-in ones.
+    let config = Config::parse(
+        r#"
+        theme = "mine"
+        [themes.mine]
+        base = "classic"
+        header = "things"
+        border = "double"
+        focus = "bold magenta"
+        marker = "=>"
+        marker-style = "green"
+        pressed = "on red"
+        heading = "italic"
+        border-style = "blue"
+        button-idle = "underlined"
+        "#,
+    )
+    .unwrap();
+    let theme = config.theme.as_ref().unwrap();
+    assert_eq!("mine", theme.name);
+    assert_eq!(border::DOUBLE, theme.border);
+    assert_eq!(Style::new().magenta().bold(), theme.focus);
+    assert_eq!(classic.highlight, theme.highlight);
+    assert_eq!(4..7, theme.checkbox_columns());
+    assert_eq!((Style::new().on_red(), Style::new().italic()), (theme.pressed, theme.heading));
+    assert_eq!(Style::new().green(), todo.fmt_item(now, &[], theme).lines[1].spans[0].style);
+    let mut app = App::new();
+    app.theme = theme.clone();
+    assert_eq!((Style::new().blue(), Style::new().underlined()), (app.get_border(&Focus::List), app.button_style(0)));
+    assert_eq!(5, config.themes.len());
+    assert!(Config::default().theme.is_none());
+
+    let errors = Config::parse(
+        r#"
+        theme = "nope"
+        [themes.a]
+        base = "neon"
+        [themes.b]
+        border = "wavy"
+        focus = "blinking"
+        glow = "on"
+        tag = 1
+        "#,
+    )
+    .unwrap_err();
+    assert_eq!(
+        vec![
+            "[themes.a]: base: expected one of: default, light, dusk, classic",
+            "[themes.b]: border: unknown border \"wavy\", expected plain, rounded, double, thick or ascii",
+            "[themes.b]: focus: unknown style \"blinking\"",
+            "[themes.b]: unknown setting \"glow\"",
+            "[themes.b]: tag: expected a string",
+            "theme: expected one of: default, light, dusk, classic, b",
+        ],
+        errors,
+    );
+
+    // Themes switch round at runtime, from a key in the list.
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.focus = Focus::List;
+    let names: Vec<String> = (0..5).map(|_| {
+        app.handle_key(KeyEvent::new(KeyCode::Char('T'), KeyModifiers::SHIFT), &mut state);
+        app.theme.name.clone()
+    }).collect();
+    assert_eq!(vec!["light", "dusk", "classic", "default", "light"], names);
+    app.focus = Focus::Input;
+    assert_eq!(app.theme.focus, app.get_border(&Focus::Input));
+}
+
+#[test]
+fn terminal_colors() {
+    // Support is guessed from the environment.
+    assert_eq!(ColorSupport::TrueColor, ColorSupport::detect(Some("truecolor"), Some("xterm-256color")));
+    assert_eq!(ColorSupport::TrueColor, ColorSupport::detect(None, Some("xterm-direct")));
+    assert_eq!(ColorSupport::Ansi256, ColorSupport::detect(None, Some("screen-256color")));
+    assert_eq!(ColorSupport::Ansi16, ColorSupport::detect(None, Some("vt100")));
+    assert_eq!(ColorSupport::Ansi16, ColorSupport::detect(None, None));
+
+    // RGB goes to the nearest color of the palette.
+    let highlight = Color::Rgb(65, 70, 80);
+    assert_eq!(highlight, ColorSupport::TrueColor.color(highlight));
+    assert_eq!(Color::Indexed(238), ColorSupport::Ansi256.color(highlight));
+    assert_eq!(Color::Indexed(196), ColorSupport::Ansi256.color(Color::Rgb(250, 10, 5)));
+    assert_eq!(Color::Indexed(67), ColorSupport::Ansi256.color(Color::Rgb(95, 135, 175)));
+    assert_eq!(Color::DarkGray, ColorSupport::Ansi16.color(highlight));
+    assert_eq!(Color::Red, ColorSupport::Ansi16.color(Color::Rgb(191, 97, 106)));
+    assert_eq!(Color::Cyan, ColorSupport::Ansi16.color(Color::Rgb(136, 192, 208)));
+    assert_eq!(Color::LightYellow, ColorSupport::Ansi16.color(Color::Rgb(235, 203, 139)));
+    assert_eq!(Color::Blue, ColorSupport::Ansi16.color(Color::Indexed(19)));
+    assert_eq!(Color::Blue, ColorSupport::Ansi16.color(Color::Blue));
+    for theme in Theme::builtin() {
+        let mut theme = theme.downsample(ColorSupport::Ansi16);
+        for style in theme.styles_mut() {
+            let colors = [style.fg, style.bg, style.underline_color];
+            assert!(!colors.iter().flatten().any(|c| matches!(c, Color::Rgb(..) | Color::Indexed(_))), "{style:?}");
+        }
+    }
+
+    // The background comes back in hex of one to four digits a channel.
+    assert_eq!(Some((30, 30, 46)), parse_background("\x1b]11;rgb:1e1e/1e1e/2e2e\x1b\\\x1b[?62;22c"));
+    assert_eq!(Some((255, 255, 255)), parse_background("\x1b]11;rgb:ffff/ffff/ffff\x07"));
+    assert_eq!(Some((255, 0, 136)), parse_background("\x1b]11;rgb:f/0/88\x07"));
+    assert_eq!(None, parse_background("\x1b[?62;22c"));
+    assert_eq!(None, parse_background("\x1b]11;rgb:ffff/ffff\x07"));
+
+    // Replies split across reads are put together, and keys typed around
+    // them are kept apart from them.
+    let mut replies = Replies::default();
+    for chunk in ["a", "\x1b]11;rg", "b:1e1e/1e1e/2e2e\x1b", "\\b\x1b[A\x1b", "[?62;", "22"] {
+        assert!(!replies.feed(chunk.as_bytes()), "{chunk:?}");
+    }
+    assert!(replies.feed(b"cd\re"));
+    assert_eq!(Some((30, 30, 46)), replies.background());
+    let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+    assert_eq!(vec![key('a'), key('b'), key('d'), KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), key('e')], replies.keys());
+    let mut replies = Replies::default();
+    assert!(!replies.feed(b"\x1b]11;rgb:ffff/ffff/ffff\x07\x1bx\x03"));
+    assert_eq!(
+        vec![KeyEvent::new(KeyCode::Char('x'), KeyModifiers::ALT), KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)],
+        replies.keys(),
+    );
+    assert!(replies.feed(b"\x1b[?1;2c"));
+    assert_eq!(Some((255, 255, 255)), replies.background());
+    assert!(is_light((253, 246, 227)));
+    assert!(!is_light((30, 30, 46)));
+    assert_eq!(Some(false), colorfgbg_is_light("15;0"));
+    assert_eq!(Some(true), colorfgbg_is_light("0;default;15"));
+    assert_eq!(None, colorfgbg_is_light("default"));
+}
+
+/// Draw the app on a test terminal, returning the screen as lines with the
+/// blank margins either side cut off, along with the styled cells.
+fn render(app: &mut App, state: &mut ListState) -> (Vec<String>, ratatui::buffer::Buffer) {
+    let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(110, 30)).unwrap();
+    terminal.draw(|frame| app.draw(frame, state)).unwrap();
+    let buffer = terminal.backend().buffer().clone();
+    let lines = (0..buffer.area.height).map(|y| {
+        let line: String = (28..82).map(|x| buffer.get(x, y).symbol()).collect();
+        line.trim_end().to_string()
+    });
+    (lines.skip(5).take(22).collect(), buffer)
+}
+
+fn render_app(theme: Theme) -> App {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.theme = theme;
+    for name in ["walk dog #pets every:week", "buy milk", "call mom"] {
+        app.new_item(name.to_string(), &mut state);
+    }
+    app.todolist[1].toggle();
+    app.todolist[0].marked = true;
+    app.focus = Focus::List;
+    app
+}
+
+#[test]
+fn render_ascii() {
+    let mut app = render_app(Theme::default().ascii());
+    let mut state = ListState::default().with_selected(Some(1));
+    let (lines, buffer) = render(&mut app, &mut state);
+    assert_eq!(
+        vec![
+            "                    T O D O M V C",
+            "",
+            "",
+            "",
+            "  +------------------------------------------------+",
+            "  | What needs to be done?                         |",
+            "  +------------------------------------------------+",
+            "   (M) Mark all as complete   (C) Clear completed",
+            "  +------------------------------------------------+",
+            "  |                                                |",
+            "  | > ( ) walk dog #pets every week                |",
+            "  |                                                |",
+            "  |                                                |",
+            "  |   (X) buy milk                                 |",
+            "  |                                                |",
+            "  |                                                |",
+            "  |   ( ) call mom                                 |",
+            "  |                                                |",
+            "  |                                                |",
+            "  |                                                |",
+            "  +-------------------------------------- 1 marked +",
+            "                                        2 items left",
+        ],
+        lines,
+    );
+    assert!(buffer.content.iter().all(|cell| cell.symbol().is_ascii()));
+
+    // Without color either, focus is shown by the border.
+    app.theme = Theme::default().ascii().monochrome();
+    let (lines, _) = render(&mut app, &mut state);
+    assert_eq!("  +------------------------------------------------+", lines[4]);
+    assert_eq!("  +================================================+", lines[8]);
+}
+
+#[test]
+fn render_monochrome() {
+    let mut app = render_app(Theme::default().monochrome());
+    let mut state = ListState::default().with_selected(Some(1));
+    let (lines, buffer) = render(&mut app, &mut state);
+    assert_eq!(
+        vec![
+            "                    T O D O M V C",
+            "",
+            "",
+            "",
+            "  ╭────────────────────────────────────────────────╮",
+            "  │ What needs to be done?                         │",
+            "  ╰────────────────────────────────────────────────╯",
+            "   (M) Mark all as complete   (C) Clear completed",
+            "  ╔════════════════════════════════════════════════╗",
+            "  ║                                                ║",
+            "  ║ ▸ ( ) walk dog #pets ↻ week                    ║",
+            "  ║                                                ║",
+            "  ║                                                ║",
+            "  ║   (X) buy milk                                 ║",
+            "  ║                                                ║",
+            "  ║                                                ║",
+            "  ║   ( ) call mom                                 ║",
+            "  ║                                                ║",
+            "  ║                                                ║",
+            "  ║                                                ║",
+            "  ╚══════════════════════════════════════ 1 marked ╝",
+            "                                        2 items left",
+        ],
+        lines,
+    );
+    assert!(buffer.content.iter().all(|cell| cell.fg == Color::Reset && cell.bg == Color::Reset));
+    // The selection is reversed and the focused border bold.
+    assert!(buffer.get(34, 18).modifier.contains(ratatui::style::Modifier::REVERSED));
+    assert!(buffer.get(30, 17).modifier.contains(ratatui::style::Modifier::BOLD));
+    assert!(!buffer.get(30, 9).modifier.contains(ratatui::style::Modifier::BOLD));
+
+    // Focus moves the double border to the input.
+    app.focus = Focus::Input;
+    let (lines, _) = render(&mut app, &mut state);
+    assert_eq!("  ╔════════════════════════════════════════════════╗", lines[4]);
+    assert_eq!("  ╭────────────────────────────────────────────────╮", lines[8]);
+}
+
+#[test]
+fn board() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    let key = |c| KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+    for name in ["walk dog #pets !high", "buy milk #shop", "call mom", "pay rent #home"] {
+        app.new_item(name.to_string(), &mut state);
+    }
+    app.todolist[2].toggle();
+    app.focus = Focus::List;
+    state.select(Some(0));
+
+    // Cards don't move from the list view, where columns can't be seen.
+    let undo = app.history.undo.len();
+    app.perform(Action::MoveCard, &mut state);
+    assert!(!app.todolist[0].started);
+    assert_eq!(undo, app.history.undo.len());
+    state.select(None);
+
+    // One key switches views, and the board's keys come before the list's.
+    app.handle_key(key('v'), &mut state);
+    assert!(app.board);
+    assert_eq!(Context::Board, app.context());
+    let text = |line: Line| line.spans.iter().map(|s| s.content.to_string()).collect::<String>();
+    let footer = text(app.keymap.footer(Context::Board, 200, Style::default()));
+    assert!(footer.contains("space/enter: move card, e: edit"), "{footer}");
+    assert!(!footer.contains("toggle complete"));
+
+    // Cards move along the columns, Done going back round to Todo.
+    let titles = |app: &App| app.columns().into_iter().map(|(title, items)| (title, items.len())).collect::<Vec<_>>();
+    let counts = |app: &App| app.columns().into_iter().map(|(_, items)| items.len()).collect::<Vec<_>>();
+    assert_eq!(vec![("Todo".to_string(), 3), ("In progress".to_string(), 0), ("Done".to_string(), 1)], titles(&app));
+    app.handle_key(key('j'), &mut state);
+    assert_eq!(Some(0), state.selected());
+    app.handle_key(key('j'), &mut state);
+    assert_eq!(Some(1), state.selected());
+    app.handle_key(key(' '), &mut state);
+    assert!(app.todolist[1].started);
+    assert_eq!(vec![2, 1, 1], counts(&app));
+    app.handle_key(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE), &mut state);
+    assert!(app.todolist[1].complete && !app.todolist[1].started);
+    app.handle_key(key(' '), &mut state);
+    assert_eq!(vec![3, 0, 1], counts(&app));
+    app.handle_key(key('u'), &mut state);
+    assert_eq!(vec![2, 0, 2], counts(&app));
+
+    // Left and right skip empty columns, keeping to the same row if they can.
+    app.handle_key(key('j'), &mut state);
+    assert_eq!(Some(2), state.selected());
+    app.handle_key(key('h'), &mut state);
+    assert_eq!(Some(3), state.selected());
+    app.handle_key(KeyEvent::new(KeyCode::Right, KeyModifiers::NONE), &mut state);
+    assert_eq!(Some(2), state.selected());
+    app.handle_key(key('l'), &mut state);
+    assert_eq!(Some(2), state.selected());
+
+    // Grouped by tag, moving a card changes its first tag, going back round
+    // from the last tag rather than to Untagged.
+    app.handle_key(key('u'), &mut state);
+    app.handle_key(key('t'), &mut state);
+    let titles = |app: &App| app.columns().into_iter().map(|(title, _)| title).collect::<Vec<_>>();
+    assert_eq!(vec!["#home", "#pets", "#shop", "Untagged"], titles(&app));
+    state.select(Some(0));
+    app.handle_key(key(' '), &mut state);
+    assert_eq!(vec!["shop"], app.todolist[0].tags);
+    app.handle_key(key(' '), &mut state);
+    assert_eq!(vec!["home"], app.todolist[0].tags);
+
+    // Other tags are kept, and untagged cards move to the first tag.
+    app.new_item("plan trip #zeta #alpha".to_string(), &mut state);
+    assert_eq!(vec!["#home", "#shop", "#zeta", "Untagged"], titles(&app));
+    state.select(Some(4));
+    app.handle_key(key(' '), &mut state);
+    assert_eq!(vec!["home", "alpha"], app.todolist[4].tags);
+    app.handle_key(key(' '), &mut state);
+    assert_eq!(vec!["shop", "alpha"], app.todolist[4].tags);
+    app.handle_key(key(' '), &mut state);
+    assert_eq!(vec!["home", "alpha"], app.todolist[4].tags);
+    state.select(Some(2));
+    app.handle_key(key(' '), &mut state);
+    assert_eq!(vec!["home"], app.todolist[2].tags);
+    app.handle_key(key('u'), &mut state);
+    app.handle_key(key('u'), &mut state);
+    app.handle_key(key('u'), &mut state);
+    app.handle_key(key('u'), &mut state);
+    assert_eq!(vec!["zeta", "alpha"], app.todolist[4].tags);
+    assert!(app.todolist[2].tags.is_empty());
+    app.handle_key(key('u'), &mut state);
+
+    // Drawn as columns of one-line cards, with the count at the bottom.
+    app.handle_key(key('t'), &mut state);
+    let (lines, _) = render(&mut app, &mut state);
+    assert_eq!(
+        vec![
+            "  ╭ Todo ────────╮╭ In progress ─╮╭ Done ──────────╮",
+            "  │walk dog !high││buy milk      ││call mom        │",
+            "  │pay rent      ││              ││                │",
+        ],
+        lines[8..11],
+    );
+    assert_eq!("  ╰─────────── 2 ╯╰─────────── 1 ╯╰───────────── 1 ╯", lines[20]);
+
+    // Clicking a card selects it, and the list comes back as it was.
+    app.areas = Areas::new(Rect::new(0, 0, 110, 30));
+    app.handle_mouse(MouseEvent {
+        kind: MouseEventKind::Down(MouseButton::Left),
+        column: 34,
+        row: 15,
+        modifiers: KeyModifiers::NONE,
+    }, &mut state);
+    assert_eq!(Some(3), state.selected());
+    app.handle_key(key('v'), &mut state);
+    assert!(!app.board);
+    app.handle_key(key('k'), &mut state);
+    assert_eq!(Some(2), state.selected());
+}
+
+#[test]
+fn render_narrow() {
+    let mut app = App::new();
+    let mut state = ListState::default();
+    app.new_item("a long todo name #tag !high due:2030-01-01".to_string(), &mut state);
+    let rows = |app: &mut App, state: &mut ListState, w, h| {
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(w, h)).unwrap();
+        terminal.draw(|frame| app.draw(frame, state)).unwrap();
+        app.areas = Areas::new(Rect::new(0, 0, w, h));
+        let buffer = terminal.backend().buffer().clone();
+        (0..h).map(|y| (0..w).map(|x| buffer.get(x, y).symbol()).collect::<String>().trim_end().to_string()).collect::<Vec<_>>()
+    };
+
+    // Margins shrink, and the footer keeps the hints that fit.
+    let lines = rows(&mut app, &mut state, 59, 30);
+    assert_eq!("         ╭───────────────────────────────────────╮", lines[9]);
+    assert_eq!("          (M) Mark all as complete  (C) Clear comp", lines[12]);
+    assert_eq!(" ctrl-c: quit, enter: save, esc: cancel, tab: switch focus", lines[29]);
+    let lines = rows(&mut app, &mut state, 30, 12);
+    assert_eq!("╰─ctrl-c: quit, enter: save──╯", lines[11]);
+    assert!(!lines.iter().any(|l| l.contains("item left")));
+
+    // Nothing panics however small, with or without overlays and the board.
+    for (w, h) in [(0, 0), (1, 1), (20, 5), (45, 17), (100, 3)] {
+        for (board, overlays) in [(false, false), (true, false), (false, true), (true, true)] {
+            app.board = board;
+            app.focus = if overlays { Focus::Input } else { Focus::List };
+            state.select(Some(0));
+            if overlays {
+                app.inputter.insert_str("typing #ta");
+                app.update_completion();
+                app.help = Some(0);
+                app.palette = Some(Palette::default());
+                app.sort_menu = Some(0);
+                app.pending_paste = Some(parse_pasted("a\nb", now()));
+            }
+            rows(&mut app, &mut state, w, h);
+        }
+    }
+}